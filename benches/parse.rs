@@ -0,0 +1,43 @@
+//! A simple timing harness comparing the allocating `FragmentGeomDesc::try_from`
+//! path with the buffer-reusing `FragmentGeomDesc::parse_into` path over a
+//! batch of 10k geometry descriptions.  Run with `cargo bench`.
+
+use seq_geom_parser::FragmentGeomDesc;
+use std::time::Instant;
+
+const NUM_DESCS: usize = 10_000;
+
+fn main() {
+    let templates = [
+        "1{b[16]u[12]x:}2{r:}",
+        "1{b[9-10]f[CAGAGC]u[8]b[10]}2{r:}",
+        "1{b[16]u[12]f[TTTTTTTTTT]x:}2{r:}",
+        "1{x[8]b[8]f[ACTGGCCTGCGA]b[8]f[GGTAGCGGTGACA]b[8]u[10]x:}2{r:}",
+    ];
+    let descs: Vec<&str> = (0..NUM_DESCS)
+        .map(|i| templates[i % templates.len()])
+        .collect();
+
+    let start = Instant::now();
+    let mut npieces = 0_usize;
+    for d in &descs {
+        let fg = FragmentGeomDesc::try_from(*d).expect("should parse");
+        npieces += fg.read1_desc().len() + fg.read2_desc().len();
+    }
+    let try_from_time = start.elapsed();
+
+    let start = Instant::now();
+    let mut npieces_reuse = 0_usize;
+    let mut fg = FragmentGeomDesc::try_from(templates[0]).expect("should parse");
+    for d in &descs {
+        fg.parse_into(d).expect("should parse");
+        npieces_reuse += fg.read1_desc().len() + fg.read2_desc().len();
+    }
+    let parse_into_time = start.elapsed();
+
+    assert_eq!(npieces, npieces_reuse);
+    println!(
+        "parsed {} descriptions ({} pieces)\n  try_from   : {:?}\n  parse_into : {:?}",
+        NUM_DESCS, npieces, try_from_time, parse_into_time
+    );
+}
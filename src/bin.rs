@@ -4,22 +4,17 @@ use anyhow::{bail, Result};
 use seq_geom_parser::{AppendToCmdArgs, FragmentGeomDesc, PiscemGeomDesc, SalmonSeparateGeomDesc};
 
 fn main() -> Result<()> {
-    let arg_owned = std::env::args().nth(1).unwrap();
-    let arg: &str = &arg_owned;
+    let arg = std::env::args().nth(1).unwrap();
     println!("arg = {}", arg);
 
-    match FragmentGeomDesc::try_from(arg) {
+    match arg.parse::<FragmentGeomDesc>() {
         Ok(frag_desc) => {
             println!("parsed geometry : {:#?}", &frag_desc);
 
             if frag_desc.is_simple_geometry() {
-                let piscem_desc =
-                    PiscemGeomDesc::from_geom_pieces(&frag_desc.read1_desc, &frag_desc.read2_desc);
+                let piscem_desc = PiscemGeomDesc::from_fragment(&frag_desc)?;
 
-                let salmon_desc = SalmonSeparateGeomDesc::from_geom_pieces(
-                    &frag_desc.read1_desc,
-                    &frag_desc.read2_desc,
-                );
+                let salmon_desc = SalmonSeparateGeomDesc::from_fragment(&frag_desc)?;
 
                 println!(
                     "salmon desc: {:?}\npiscem_desc: {:?}",
@@ -36,10 +31,10 @@ fn main() -> Result<()> {
             }
             /*
             let piscem_desc =
-                PiscemGeomDesc::from_geom_pieces(&frag_desc.read1_desc, &frag_desc.read2_desc);
+                PiscemGeomDesc::from_geom_pieces(frag_desc.read1_desc(), frag_desc.read2_desc());
             let salmon_desc = SalmonSeparateGeomDesc::from_geom_pieces(
-                &frag_desc.read1_desc,
-                &frag_desc.read2_desc,
+                frag_desc.read1_desc(),
+                frag_desc.read2_desc(),
             );
 
             println!(
@@ -13,51 +13,439 @@ extern crate pest;
 #[macro_use]
 extern crate pest_derive;
 
-use anyhow::{anyhow, bail, Result};
+use anyhow::{bail, Result};
 use pest::Parser;
 
 use std::convert::TryFrom;
 use std::fmt;
+use std::str::FromStr;
 
 #[derive(Parser)]
 #[grammar = "grammar/frag_geom.pest"] // relative to src
 pub struct FragGeomParser;
 
+/// The ways in which parsing or constructing a fragment geometry
+/// description can fail.  This implements `std::error::Error`, so it
+/// converts directly into an `anyhow::Error` (e.g. via `?`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FragmentGeomError {
+    /// The description string could not be parsed according to the grammar.
+    /// `pos` is the byte offset in the input at which parsing failed, and `msg` is
+    /// a diagnostic pointing at the offending column (see [`format_parse_error`]).
+    ParseFailure { pos: usize, msg: String },
+    /// A ranged length has a lower bound greater than its upper bound.
+    /// `segment` is the canonical representation of the offending segment.
+    InvalidRange {
+        segment: String,
+        low: u32,
+        high: u32,
+    },
+    /// A fixed sequence is empty or contains characters outside of the IUPAC
+    /// nucleotide alphabet (`ACGTURYSWKMBDHVN`).
+    NonACGTFixedSeq(String),
+    /// The description of the given (required) read is missing or empty.
+    MissingRead(u8),
+    /// The name of a custom tag is empty or contains characters other than
+    /// ASCII letters, digits, and `_`.
+    InvalidTagName(String),
+    /// The base of a homopolymer segment is not one of `A`, `C`, `G`, or `T`.
+    InvalidHomopolymerBase(char),
+    /// A segment has a length of 0 (or a range with a lower bound of 0).
+    /// `segment` is the canonical representation of the offending segment.
+    ZeroLength { segment: String },
+    /// The given read contains two unbounded segments that are not separated by a
+    /// fixed sequence anchor.  Index reads I1 and I2 are numbered after the last
+    /// read of the fragment (i.e. as reads 3 and 4 for a paired-end fragment).
+    MultipleUnbounded { read: u8 },
+    /// The given read contains two adjacent variable-length (ranged, open-ended, or
+    /// unbounded) pieces, at the 0-based positions `first` and `second`, with no
+    /// fixed-length piece or fixed sequence anchor between them to delimit them.
+    AmbiguousLayout {
+        read: u8,
+        first: usize,
+        second: usize,
+    },
+    /// The description has an unbalanced brace (`{`/`}`) or bracket (`[`/`]`) in the
+    /// given read block.  Blocks are numbered by position, so index read blocks are
+    /// numbered after the read blocks.
+    UnbalancedBraces { read: u8 },
+    /// The geometry describes `found` reads, but the target representation (e.g.
+    /// the geometry format of some tool) can represent at most `max` reads.  Parsing
+    /// and validation also return this when the read and index read blocks number
+    /// more than `u8::MAX`.
+    TooManyReads { found: usize, max: usize },
+    /// The given read, of length `got`, is too short to hold the `needed` bases
+    /// required by its description.
+    ReadTooShort { read: u8, needed: u32, got: u32 },
+    /// A fixed sequence of length `len` exceeds the maximum length `max` allowed by
+    /// the `ParseOpts` in use.
+    FixedSeqTooLong { len: usize, max: usize },
+    /// A segment has a length (or, for a range, an upper bound) greater than the
+    /// maximum length `max` allowed by the `ParseOpts` in use.  `segment` is the
+    /// canonical representation of the offending segment.
+    SegmentTooLong { segment: String, max: u32 },
+    /// The cumulative length of the pieces of a read, up to and including `segment`,
+    /// exceeds `u32::MAX`, so the offsets of its pieces can't be represented.
+    OffsetOverflow { segment: String },
+    /// The description is a valid geometry up to the byte offset `at`, but is followed
+    /// by the unexpected trailing text `text` (e.g. a copy-paste leftover).
+    TrailingInput { at: usize, text: String },
+    /// The 0-based piece position `index` is out of bounds for the given read, which
+    /// has `len` pieces.
+    PieceIndexOutOfRange { read: u8, index: usize, len: usize },
+    /// The UMI is split into more than one region; that is, into UMI segments that are
+    /// not contiguous within a single read.  `reads` gives the read of each region, in
+    /// order, so e.g. `1{u[12]}2{u[8]r:}` has UMI regions in reads `[1, 2]`.
+    MultipleUmiRegions { reads: Vec<u8> },
+    /// A fixed sequence contains the base `base`, which isn't part of the `alphabet`
+    /// allowed by the `ParseOpts` in use.
+    InvalidBase { base: char, alphabet: Alphabet },
+    /// The fixed sequence `segment` tolerates `max_mismatch` mismatches, but has only
+    /// `len` bases, so it would match any sequence.
+    TooManyMismatches {
+        segment: String,
+        max_mismatch: u32,
+        len: usize,
+    },
+}
+
+impl fmt::Display for FragmentGeomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FragmentGeomError::ParseFailure { pos, msg } => write!(
+                f,
+                "could not parse geometry description at position {}:\n{}",
+                pos, msg
+            ),
+            FragmentGeomError::InvalidRange { segment, low, high } => write!(
+                f,
+                "the range {}-{} of segment {} has a lower bound greater than its upper bound",
+                low, high, segment
+            ),
+            FragmentGeomError::NonACGTFixedSeq(s) => write!(
+                f,
+                "the fixed sequence \"{}\" must be non-empty and contain only IUPAC nucleotide codes",
+                s
+            ),
+            FragmentGeomError::MissingRead(r) => {
+                write!(f, "the description of read {} is missing or empty", r)
+            }
+            FragmentGeomError::ZeroLength { segment } => {
+                write!(f, "the segment {} has a length of 0", segment)
+            }
+            FragmentGeomError::MultipleUnbounded { read } => write!(
+                f,
+                "read {} contains multiple unbounded segments not separated by a fixed sequence",
+                read
+            ),
+            FragmentGeomError::InvalidTagName(n) => write!(
+                f,
+                "the tag name \"{}\" must be non-empty and contain only ASCII letters, digits, or _",
+                n
+            ),
+            FragmentGeomError::InvalidHomopolymerBase(b) => write!(
+                f,
+                "the homopolymer base '{}' must be one of A, C, G, or T",
+                b
+            ),
+            FragmentGeomError::AmbiguousLayout {
+                read,
+                first,
+                second,
+            } => write!(
+                f,
+                "read {} has adjacent variable-length segments at positions {} and {}, which can't be delimited",
+                read, first, second
+            ),
+            FragmentGeomError::UnbalancedBraces { read } => write!(
+                f,
+                "the description of read {} has an unclosed or unmatched brace or bracket",
+                read
+            ),
+            FragmentGeomError::TooManyReads { found, max } => write!(
+                f,
+                "the geometry describes {} reads, but at most {} can be represented",
+                found, max
+            ),
+            FragmentGeomError::ReadTooShort { read, needed, got } => write!(
+                f,
+                "read {} has length {}, but its description requires at least {} bases",
+                read, got, needed
+            ),
+            FragmentGeomError::FixedSeqTooLong { len, max } => write!(
+                f,
+                "a fixed sequence has length {}, but at most {} is allowed",
+                len, max
+            ),
+            FragmentGeomError::SegmentTooLong { segment, max } => write!(
+                f,
+                "the segment {} is longer than the maximum allowed length of {}",
+                segment, max
+            ),
+            FragmentGeomError::OffsetOverflow { segment } => write!(
+                f,
+                "the offset of the end of segment {} exceeds the largest representable offset",
+                segment
+            ),
+            FragmentGeomError::TrailingInput { at, text } => write!(
+                f,
+                "the geometry description is valid up to position {}, but is followed by the unexpected text \"{}\"",
+                at, text
+            ),
+            FragmentGeomError::PieceIndexOutOfRange { read, index, len } => write!(
+                f,
+                "piece position {} is out of bounds for read {}, which has {} pieces",
+                index, read, len
+            ),
+            FragmentGeomError::MultipleUmiRegions { reads } => write!(
+                f,
+                "the UMI is split into {} separate regions, in reads {:?}",
+                reads.len(),
+                reads
+            ),
+            FragmentGeomError::InvalidBase { base, alphabet } => write!(
+                f,
+                "the base '{}' of a fixed sequence is not allowed by the {:?} alphabet",
+                base, alphabet
+            ),
+            FragmentGeomError::TooManyMismatches {
+                segment,
+                max_mismatch,
+                len,
+            } => write!(
+                f,
+                "the fixed sequence {} tolerates {} mismatches, but has only {} bases",
+                segment, max_mismatch, len
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FragmentGeomError {}
+
 /// The types of lengths that a piece of
-/// geometry can have.
-#[derive(Debug, Copy, Clone)]
+/// geometry can have.  Lengths are ordered first by variant, in the order in
+/// which they are declared (so every fixed length precedes every range), and
+/// then by their bounds.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GeomLen {
     /// This piece of geometry has a single fixed length
     FixedLen(u32),
     /// This piece of geometry has some length between
     /// a provided lower and upper bound
     LenRange(u32, u32),
+    /// This piece of geometry has a length of at least
+    /// the provided lower bound, but no upper bound
+    LenRangeFrom(u32),
     /// This piece of geometry has a length whose bound is
     /// not known at geometry specification time
     Unbounded,
 }
 
+impl GeomLen {
+    /// Returns the minimum length of a piece having this length (0 for `Unbounded`).
+    pub fn min_len(&self) -> u32 {
+        match self {
+            GeomLen::FixedLen(x) => *x,
+            GeomLen::LenRange(l, _) | GeomLen::LenRangeFrom(l) => *l,
+            GeomLen::Unbounded => 0,
+        }
+    }
+
+    /// Returns the maximum length of a piece having this length, or `None` if it
+    /// has no upper bound (i.e. for `Unbounded` and `LenRangeFrom`).
+    pub fn max_len(&self) -> Option<u32> {
+        match self {
+            GeomLen::FixedLen(x) => Some(*x),
+            GeomLen::LenRange(_, h) => Some(*h),
+            GeomLen::LenRangeFrom(_) | GeomLen::Unbounded => None,
+        }
+    }
+
+    /// Returns true if this length isn't a single fixed value; that is, for
+    /// `LenRange`, `LenRangeFrom`, and `Unbounded`.
+    pub fn is_variable(&self) -> bool {
+        !matches!(self, GeomLen::FixedLen(_))
+    }
+}
+
 /// Represents the sequence held by a fixed
-/// sequence anchor.
-#[derive(Debug, Clone)]
+/// sequence anchor.  The sequence may contain
+/// IUPAC ambiguity codes (e.g. `N`, `R`, `Y`).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum NucStr {
     Seq(String),
 }
 
+/// Returns true if `c` is one of the IUPAC nucleotide codes (`ACGTURYSWKMBDHVN`).
+fn is_iupac_code(c: u8) -> bool {
+    matches!(
+        c,
+        b'A' | b'C'
+            | b'G'
+            | b'T'
+            | b'U'
+            | b'R'
+            | b'Y'
+            | b'S'
+            | b'W'
+            | b'K'
+            | b'M'
+            | b'B'
+            | b'D'
+            | b'H'
+            | b'V'
+            | b'N'
+    )
+}
+
+/// Returns true if the concrete nucleotide `base` (one of `A`, `C`, `G`, `T` or
+/// `U`, in either case) is one of the nucleotides represented by the IUPAC code `code`.
+/// `U` and `T` are treated as equivalent.
+pub fn iupac_base_matches(code: u8, base: u8) -> bool {
+    let base = match base.to_ascii_uppercase() {
+        b'U' => b'T',
+        b => b,
+    };
+    match code {
+        b'A' => base == b'A',
+        b'C' => base == b'C',
+        b'G' => base == b'G',
+        b'T' | b'U' => base == b'T',
+        b'R' => matches!(base, b'A' | b'G'),
+        b'Y' => matches!(base, b'C' | b'T'),
+        b'S' => matches!(base, b'G' | b'C'),
+        b'W' => matches!(base, b'A' | b'T'),
+        b'K' => matches!(base, b'G' | b'T'),
+        b'M' => matches!(base, b'A' | b'C'),
+        b'B' => matches!(base, b'C' | b'G' | b'T'),
+        b'D' => matches!(base, b'A' | b'G' | b'T'),
+        b'H' => matches!(base, b'A' | b'C' | b'T'),
+        b'V' => matches!(base, b'A' | b'C' | b'G'),
+        b'N' => matches!(base, b'A' | b'C' | b'G' | b'T'),
+        _ => false,
+    }
+}
+
+impl NucStr {
+    /// Returns true if the concrete sequence `seq` matches this sequence, expanding
+    /// any IUPAC ambiguity codes at each position (see [`iupac_base_matches`]).
+    /// `seq` must have the same length as this sequence to match.  A `NucStr` may
+    /// hold several codes, so a single concrete base is checked against one of them
+    /// with [`iupac_base_matches`] rather than against the whole sequence.
+    pub fn matches(&self, seq: &[u8]) -> bool {
+        match self {
+            NucStr::Seq(s) => {
+                s.len() == seq.len()
+                    && s.bytes()
+                        .zip(seq.iter())
+                        .all(|(code, base)| iupac_base_matches(code, *base))
+            }
+        }
+    }
+
+    /// Returns the reverse complement of this sequence.  IUPAC ambiguity codes are
+    /// complemented to the code representing the complementary set of bases (e.g.
+    /// `R` becomes `Y`).  The complement is taken as DNA, so a `U` becomes `A`, which
+    /// in turn becomes `T`; reverse complementing twice therefore gives back the
+    /// original sequence only if it contains no `U`.
+    pub fn reverse_complement(&self) -> NucStr {
+        match self {
+            NucStr::Seq(s) => NucStr::Seq(
+                s.bytes()
+                    .rev()
+                    .map(|c| match c {
+                        b'A' => 'T',
+                        b'C' => 'G',
+                        b'G' => 'C',
+                        b'T' | b'U' => 'A',
+                        b'R' => 'Y',
+                        b'Y' => 'R',
+                        b'S' => 'S',
+                        b'W' => 'W',
+                        b'K' => 'M',
+                        b'M' => 'K',
+                        b'B' => 'V',
+                        b'V' => 'B',
+                        b'D' => 'H',
+                        b'H' => 'D',
+                        c => c as char,
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Returns true if this sequence contains any ambiguity codes (i.e. anything
+    /// other than `A`, `C`, `G` and `T`).
+    pub fn is_ambiguous(&self) -> bool {
+        match self {
+            NucStr::Seq(s) => !s.bytes().all(|c| matches!(c, b'A' | b'C' | b'G' | b'T')),
+        }
+    }
+}
+
 /// The pieces of geometry (types) we
 /// currently support.
-#[derive(Debug, Clone)]
+///
+/// Pieces are totally ordered first by kind, in the order in which the variants
+/// are declared below (barcode, sample barcode, UMI, discard, read, fixed, tag,
+/// homopolymer, spacer), and then by length as ordered by `GeomLen`.  Fixed sequences are
+/// ordered by their sequence and then their mismatch tolerance, while tags and
+/// homopolymers are ordered by their name or base before their length.  Sorting
+/// the pieces of a read by this order discards their layout, so it is only
+/// useful for comparing which pieces a geometry has (see
+/// [`FragmentGeomDesc::piece_multiset_eq`]).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GeomPiece {
     /// A cellular barcode
     Barcode(GeomLen),
+    /// A sample (e.g. hashing or multiplexing) barcode, distinct from
+    /// the cellular barcode
+    SampleBarcode(GeomLen),
     /// A unique molecular identifier
     Umi(GeomLen),
     /// Sequence that will be discarded
     Discard(GeomLen),
-    /// Biological read sequence
-    ReadSeq(GeomLen),
-    /// A fixed sequence anchor / motif
-    Fixed(NucStr),
+    /// Biological read sequence, along with an optional pairing id (written
+    /// `r#n`) that links read segments describing the same biological
+    /// molecule, e.g. `1{b[16]u[12]r#1:}2{r#1:}`.  The id is not part of the
+    /// layout, and is ignored by the length and type predicates.
+    ReadSeq(GeomLen, Option<u32>),
+    /// A fixed sequence anchor / motif, along with the maximum number of
+    /// mismatches (Hamming distance) tolerated when matching it
+    Fixed(NucStr, u32),
+    /// A custom region, carrying a user-provided name, that should be
+    /// preserved but that isn't a barcode, UMI, read, or discard
+    Tag(String, GeomLen),
+    /// A homopolymer stretch (e.g. a poly-A or poly-T tail) of the
+    /// given base (one of `A`, `C`, `G`, or `T`)
+    Homopolymer(u8, GeomLen),
+    /// A spacer whose content varies, but whose length is meaningful and
+    /// should be reported (unlike `Discard`).  Tools with no notion of a
+    /// spacer treat it as discarded sequence of the same length.
+    Spacer(GeomLen),
+}
+
+/// The type of a `GeomPiece`, without its length or sequence.  This
+/// is returned by [`GeomPiece::kind`], and allows matching on the type
+/// of a piece without enumerating every combination of variant and
+/// `GeomLen`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SegmentKind {
+    Barcode,
+    SampleBarcode,
+    Umi,
+    Discard,
+    ReadSeq,
+    Fixed,
+    Tag,
+    Homopolymer,
+    Spacer,
 }
 
 impl fmt::Display for GeomPiece {
@@ -67,17 +455,50 @@ impl fmt::Display for GeomPiece {
         match &self {
             GeomPiece::Umi(GeomLen::Unbounded) => write!(f, "u:"),
             GeomPiece::Barcode(GeomLen::Unbounded) => write!(f, "b:"),
-            GeomPiece::ReadSeq(GeomLen::Unbounded) => write!(f, "r:"),
+            GeomPiece::SampleBarcode(GeomLen::Unbounded) => write!(f, "s:"),
+            GeomPiece::ReadSeq(GeomLen::Unbounded, None) => write!(f, "r:"),
             GeomPiece::Discard(GeomLen::Unbounded) => write!(f, "x:"),
             GeomPiece::Umi(GeomLen::FixedLen(x)) => write!(f, "u[{}]", x),
             GeomPiece::Barcode(GeomLen::FixedLen(x)) => write!(f, "b[{}]", x),
-            GeomPiece::ReadSeq(GeomLen::FixedLen(x)) => write!(f, "r[{}]", x),
+            GeomPiece::SampleBarcode(GeomLen::FixedLen(x)) => write!(f, "s[{}]", x),
+            GeomPiece::ReadSeq(GeomLen::FixedLen(x), None) => write!(f, "r[{}]", x),
             GeomPiece::Discard(GeomLen::FixedLen(x)) => write!(f, "x[{}]", x),
             GeomPiece::Umi(GeomLen::LenRange(l, h)) => write!(f, "u[{}-{}]", l, h),
             GeomPiece::Barcode(GeomLen::LenRange(l, h)) => write!(f, "b[{}-{}]", l, h),
-            GeomPiece::ReadSeq(GeomLen::LenRange(l, h)) => write!(f, "r[{}-{}]", l, h),
+            GeomPiece::SampleBarcode(GeomLen::LenRange(l, h)) => write!(f, "s[{}-{}]", l, h),
+            GeomPiece::ReadSeq(GeomLen::LenRange(l, h), None) => write!(f, "r[{}-{}]", l, h),
             GeomPiece::Discard(GeomLen::LenRange(l, h)) => write!(f, "x[{}-{}]", l, h),
-            GeomPiece::Fixed(NucStr::Seq(s)) => write!(f, "f[{}]", s),
+            GeomPiece::Umi(GeomLen::LenRangeFrom(l)) => write!(f, "u[{}-]", l),
+            GeomPiece::Barcode(GeomLen::LenRangeFrom(l)) => write!(f, "b[{}-]", l),
+            GeomPiece::SampleBarcode(GeomLen::LenRangeFrom(l)) => write!(f, "s[{}-]", l),
+            GeomPiece::ReadSeq(GeomLen::LenRangeFrom(l), None) => write!(f, "r[{}-]", l),
+            GeomPiece::Discard(GeomLen::LenRangeFrom(l)) => write!(f, "x[{}-]", l),
+            GeomPiece::ReadSeq(GeomLen::Unbounded, Some(id)) => write!(f, "r#{}:", id),
+            GeomPiece::ReadSeq(GeomLen::FixedLen(x), Some(id)) => write!(f, "r#{}[{}]", id, x),
+            GeomPiece::ReadSeq(GeomLen::LenRange(l, h), Some(id)) => {
+                write!(f, "r#{}[{}-{}]", id, l, h)
+            }
+            GeomPiece::ReadSeq(GeomLen::LenRangeFrom(l), Some(id)) => write!(f, "r#{}[{}-]", id, l),
+            GeomPiece::Fixed(NucStr::Seq(s), 0) => write!(f, "f[{}]", s),
+            GeomPiece::Fixed(NucStr::Seq(s), m) => write!(f, "f[{}]~{}", s, m),
+            GeomPiece::Tag(n, GeomLen::Unbounded) => write!(f, "t<{}>:", n),
+            GeomPiece::Tag(n, GeomLen::FixedLen(x)) => write!(f, "t<{}>[{}]", n, x),
+            GeomPiece::Tag(n, GeomLen::LenRange(l, h)) => write!(f, "t<{}>[{}-{}]", n, l, h),
+            GeomPiece::Tag(n, GeomLen::LenRangeFrom(l)) => write!(f, "t<{}>[{}-]", n, l),
+            GeomPiece::Homopolymer(b, GeomLen::Unbounded) => write!(f, "p<{}>:", *b as char),
+            GeomPiece::Homopolymer(b, GeomLen::FixedLen(x)) => {
+                write!(f, "p<{}>[{}]", *b as char, x)
+            }
+            GeomPiece::Homopolymer(b, GeomLen::LenRange(l, h)) => {
+                write!(f, "p<{}>[{}-{}]", *b as char, l, h)
+            }
+            GeomPiece::Homopolymer(b, GeomLen::LenRangeFrom(l)) => {
+                write!(f, "p<{}>[{}-]", *b as char, l)
+            }
+            GeomPiece::Spacer(GeomLen::Unbounded) => write!(f, "g:"),
+            GeomPiece::Spacer(GeomLen::FixedLen(x)) => write!(f, "g[{}]", x),
+            GeomPiece::Spacer(GeomLen::LenRange(l, h)) => write!(f, "g[{}-{}]", l, h),
+            GeomPiece::Spacer(GeomLen::LenRangeFrom(l)) => write!(f, "g[{}-]", l),
         }
     }
 }
@@ -90,36 +511,126 @@ impl GeomPiece {
             self,
             GeomPiece::Umi(GeomLen::FixedLen(_))
                 | GeomPiece::Barcode(GeomLen::FixedLen(_))
-                | GeomPiece::ReadSeq(GeomLen::FixedLen(_))
+                | GeomPiece::SampleBarcode(GeomLen::FixedLen(_))
+                | GeomPiece::ReadSeq(GeomLen::FixedLen(_), _)
                 | GeomPiece::Discard(GeomLen::FixedLen(_))
-                | GeomPiece::Fixed(NucStr::Seq(_))
+                | GeomPiece::Tag(_, GeomLen::FixedLen(_))
+                | GeomPiece::Homopolymer(_, GeomLen::FixedLen(_))
+                | GeomPiece::Spacer(GeomLen::FixedLen(_))
+                | GeomPiece::Fixed(NucStr::Seq(_), _)
         )
     }
 
     /// This method returns true if the current GeomPiece has a bounded length
-    /// (either Bounded, BoundedRange, or a Fixed(NucStr)), and false otherwise.
+    /// (either Bounded, BoundedRange, or a Fixed(NucStr)), and false otherwise
+    /// (i.e. for Unbounded and open-ended LenRangeFrom pieces).
     pub fn is_bounded(&self) -> bool {
         !matches!(
             self,
-            GeomPiece::Umi(GeomLen::Unbounded)
-                | GeomPiece::Barcode(GeomLen::Unbounded)
-                | GeomPiece::ReadSeq(GeomLen::Unbounded)
-                | GeomPiece::Discard(GeomLen::Unbounded)
+            GeomPiece::Umi(GeomLen::Unbounded | GeomLen::LenRangeFrom(_))
+                | GeomPiece::Barcode(GeomLen::Unbounded | GeomLen::LenRangeFrom(_))
+                | GeomPiece::SampleBarcode(GeomLen::Unbounded | GeomLen::LenRangeFrom(_))
+                | GeomPiece::ReadSeq(GeomLen::Unbounded | GeomLen::LenRangeFrom(_), _)
+                | GeomPiece::Discard(GeomLen::Unbounded | GeomLen::LenRangeFrom(_))
+                | GeomPiece::Tag(_, GeomLen::Unbounded | GeomLen::LenRangeFrom(_))
+                | GeomPiece::Homopolymer(_, GeomLen::Unbounded | GeomLen::LenRangeFrom(_))
+                | GeomPiece::Spacer(GeomLen::Unbounded | GeomLen::LenRangeFrom(_))
         )
     }
 
     /// This method returns true if the current GeomPiece is "complex"
-    /// (either BoundedRange, or a Fixed(NucStr)), and false otherwise.
+    /// (either BoundedRange, LenRangeFrom, or a Fixed(NucStr)), and false otherwise.
     pub fn is_complex(&self) -> bool {
         matches!(
             self,
-            GeomPiece::Fixed(NucStr::Seq(_))
-                | GeomPiece::Umi(GeomLen::LenRange(_, _))
-                | GeomPiece::Barcode(GeomLen::LenRange(_, _))
-                | GeomPiece::ReadSeq(GeomLen::LenRange(_, _))
-                | GeomPiece::Discard(GeomLen::LenRange(_, _))
+            GeomPiece::Fixed(NucStr::Seq(_), _)
+                | GeomPiece::Umi(GeomLen::LenRange(_, _) | GeomLen::LenRangeFrom(_))
+                | GeomPiece::Barcode(GeomLen::LenRange(_, _) | GeomLen::LenRangeFrom(_))
+                | GeomPiece::SampleBarcode(GeomLen::LenRange(_, _) | GeomLen::LenRangeFrom(_))
+                | GeomPiece::ReadSeq(GeomLen::LenRange(_, _) | GeomLen::LenRangeFrom(_), _)
+                | GeomPiece::Discard(GeomLen::LenRange(_, _) | GeomLen::LenRangeFrom(_))
+                | GeomPiece::Tag(_, GeomLen::LenRange(_, _) | GeomLen::LenRangeFrom(_))
+                | GeomPiece::Homopolymer(_, GeomLen::LenRange(_, _) | GeomLen::LenRangeFrom(_))
+                | GeomPiece::Spacer(GeomLen::LenRange(_, _) | GeomLen::LenRangeFrom(_))
         )
     }
+
+    /// Returns the length of this piece; the `GeomLen` it carries, or, for a fixed
+    /// sequence anchor, the `FixedLen` of the sequence.
+    pub fn geom_len(&self) -> GeomLen {
+        match self {
+            GeomPiece::Barcode(gl)
+            | GeomPiece::SampleBarcode(gl)
+            | GeomPiece::Umi(gl)
+            | GeomPiece::Discard(gl)
+            | GeomPiece::ReadSeq(gl, _)
+            | GeomPiece::Tag(_, gl)
+            | GeomPiece::Homopolymer(_, gl)
+            | GeomPiece::Spacer(gl) => *gl,
+            GeomPiece::Fixed(NucStr::Seq(s), _) => GeomLen::FixedLen(s.len() as u32),
+        }
+    }
+
+    /// Returns the type of this piece.
+    pub fn kind(&self) -> SegmentKind {
+        match self {
+            GeomPiece::Barcode(_) => SegmentKind::Barcode,
+            GeomPiece::SampleBarcode(_) => SegmentKind::SampleBarcode,
+            GeomPiece::Umi(_) => SegmentKind::Umi,
+            GeomPiece::Discard(_) => SegmentKind::Discard,
+            GeomPiece::ReadSeq(_, _) => SegmentKind::ReadSeq,
+            GeomPiece::Fixed(_, _) => SegmentKind::Fixed,
+            GeomPiece::Tag(_, _) => SegmentKind::Tag,
+            GeomPiece::Homopolymer(_, _) => SegmentKind::Homopolymer,
+            GeomPiece::Spacer(_) => SegmentKind::Spacer,
+        }
+    }
+
+    /// Returns a piece of the same type as this one, but with length `new`.  Since the
+    /// length of a fixed sequence anchor is determined by its sequence, this returns
+    /// an unchanged copy of a `Fixed` piece.
+    pub fn with_len(&self, new: GeomLen) -> GeomPiece {
+        match self {
+            GeomPiece::Barcode(_) => GeomPiece::Barcode(new),
+            GeomPiece::SampleBarcode(_) => GeomPiece::SampleBarcode(new),
+            GeomPiece::Umi(_) => GeomPiece::Umi(new),
+            GeomPiece::ReadSeq(_, id) => GeomPiece::ReadSeq(new, *id),
+            GeomPiece::Discard(_) => GeomPiece::Discard(new),
+            GeomPiece::Tag(name, _) => GeomPiece::Tag(name.clone(), new),
+            GeomPiece::Homopolymer(b, _) => GeomPiece::Homopolymer(*b, new),
+            GeomPiece::Spacer(_) => GeomPiece::Spacer(new),
+            GeomPiece::Fixed(_, _) => self.clone(),
+        }
+    }
+
+    /// Returns a `Discard` piece consuming the same bases as this one; that is, one
+    /// carrying the same `GeomLen`, or, for a fixed sequence anchor, the `FixedLen` of
+    /// its sequence.  This masks a segment (e.g. a UMI that a tool should ignore)
+    /// without changing the offsets of the pieces that follow it.
+    pub fn as_discard(&self) -> GeomPiece {
+        GeomPiece::Discard(self.geom_len())
+    }
+
+    /// Returns the reverse complement of this piece.  For a fixed sequence anchor
+    /// this reverse complements the sequence (keeping its mismatch tolerance), and for
+    /// a homopolymer this complements its base, while pieces that carry only a length
+    /// are returned unchanged.
+    pub fn reverse_complement(&self) -> GeomPiece {
+        match self {
+            GeomPiece::Fixed(ns, m) => GeomPiece::Fixed(ns.reverse_complement(), *m),
+            GeomPiece::Homopolymer(b, gl) => {
+                let cb = match b {
+                    b'A' => b'T',
+                    b'C' => b'G',
+                    b'G' => b'C',
+                    b'T' => b'A',
+                    b => *b,
+                };
+                GeomPiece::Homopolymer(cb, *gl)
+            }
+            gp => gp.clone(),
+        }
+    }
 }
 
 // functions for parsing the different types of geometry elements
@@ -129,9 +640,7 @@ impl GeomPiece {
 fn parse_fixed_len_as_u32(r: &mut pest::iterators::Pairs<Rule>) -> u32 {
     let rn = r.next().unwrap();
     match rn.as_rule() {
-        Rule::single_len => {
-            return rn.as_str().parse::<u32>().unwrap();
-        }
+        Rule::single_len => rn.as_str().parse::<u32>().unwrap(),
         r => unimplemented!("Expected rule 'single_len', but found {:?}", r),
     }
 }
@@ -143,7 +652,10 @@ fn parse_fixed_len(r: &mut pest::iterators::Pairs<Rule>) -> GeomLen {
 }
 
 /// Parses a range of the format, "l-h" (where "l" and "h" assumed to be parsable as a `u32`)
-/// and returns `GeomLen::LenRange(l, h)`.
+/// and returns `GeomLen::LenRange(l, h)`.  A range where `l == h` describes a piece of
+/// fixed length, and so is normalized to `GeomLen::FixedLen(l)`.  A range where `l > h`
+/// is returned as-is, and is rejected when the parsed geometry is validated.  An
+/// open-ended range of the format "l-" is returned as `GeomLen::LenRangeFrom(l)`.
 fn parse_ranged_len(r: &mut pest::iterators::Pairs<Rule>) -> GeomLen {
     let rn = r.next().unwrap();
     match rn.as_rule() {
@@ -151,25 +663,93 @@ fn parse_ranged_len(r: &mut pest::iterators::Pairs<Rule>) -> GeomLen {
             let mut ri = rn.into_inner();
             let l = parse_fixed_len_as_u32(&mut ri);
             let h = parse_fixed_len_as_u32(&mut ri);
-            GeomLen::LenRange(l, h)
+            if l == h {
+                GeomLen::FixedLen(l)
+            } else {
+                GeomLen::LenRange(l, h)
+            }
+        }
+        Rule::len_from => {
+            let mut ri = rn.into_inner();
+            GeomLen::LenRangeFrom(parse_fixed_len_as_u32(&mut ri))
         }
-        r => unimplemented!("expected rule 'len_range' but found {:?}", r),
+        r => unimplemented!("expected rule 'len_range' or 'len_from' but found {:?}", r),
     }
 }
 
-/// Parses a fixed nucleotide sequence s (matching "[ACGT]+") and returns
-/// `NucStr::Seq(s)`.
+/// Parses the optional pairing id "#n" of a read segment, returning `Some(n)` if it
+/// is present (and consuming it), and `None` otherwise.
+fn parse_read_pair_id(r: &mut pest::iterators::Pairs<Rule>) -> Option<u32> {
+    match r.peek() {
+        Some(rn) if rn.as_rule() == Rule::read_pair_id => {
+            r.next();
+            Some(rn.as_str().parse::<u32>().unwrap())
+        }
+        _ => None,
+    }
+}
+
+/// Parses a fixed nucleotide sequence s (matching "[ACGTURYSWKMBDHVN]+", case-insensitively)
+/// and returns `NucStr::Seq(s)`, with s normalized to uppercase.  Any base may be followed
+/// by a repetition count `{n}` (with 1 <= n <= 9999), which applies to that single base only,
+/// so e.g. `AN{3}G` is expanded to `ANNNG` here, and `AC{2}` to `ACC` (not `ACAC`).
 fn parse_fixed_seq(r: &mut pest::iterators::Pairs<Rule>) -> NucStr {
     let rn = r.next().unwrap();
     match rn.as_rule() {
         Rule::nucstr => {
-            let seq_str = rn.as_str();
-            NucStr::Seq(seq_str.to_owned())
+            let mut seq_str = String::new();
+            let mut last_base = 'N';
+            for part in rn.into_inner() {
+                match part.as_rule() {
+                    Rule::nuc_base => {
+                        last_base = part.as_str().chars().next().unwrap().to_ascii_uppercase();
+                        seq_str.push(last_base);
+                    }
+                    Rule::nuc_count => {
+                        // the base itself has already been pushed once
+                        let count = part.as_str().parse::<usize>().unwrap();
+                        for _ in 1..count {
+                            seq_str.push(last_base);
+                        }
+                    }
+                    r => unimplemented!("expected a base or repetition count but found {:?}", r),
+                }
+            }
+            NucStr::Seq(seq_str)
         }
         r => unimplemented!("expected rule 'nucstr' but found {:?}", r),
     }
 }
 
+/// Parses the optional mismatch suffix "~m" that may follow a fixed sequence, and
+/// returns m (or 0 if there is no suffix).
+fn parse_max_mismatch(r: &mut pest::iterators::Pairs<Rule>) -> u32 {
+    match r.next() {
+        Some(rn) if rn.as_rule() == Rule::max_mismatch => rn.as_str().parse::<u32>().unwrap(),
+        Some(rn) => unimplemented!("expected rule 'max_mismatch' but found {:?}", rn.as_rule()),
+        None => 0,
+    }
+}
+
+/// Parses the name s of a custom tag (matching "[A-Za-z0-9_]+") and returns it.
+fn parse_tag_name(r: &mut pest::iterators::Pairs<Rule>) -> String {
+    let rn = r.next().unwrap();
+    match rn.as_rule() {
+        Rule::tag_name => rn.as_str().to_owned(),
+        r => unimplemented!("expected rule 'tag_name' but found {:?}", r),
+    }
+}
+
+/// Parses the base b of a homopolymer (matching "[ACGT]", case-insensitively) and
+/// returns it as an uppercase ASCII byte.
+fn parse_hp_base(r: &mut pest::iterators::Pairs<Rule>) -> u8 {
+    let rn = r.next().unwrap();
+    match rn.as_rule() {
+        Rule::hp_base => rn.as_str().as_bytes()[0].to_ascii_uppercase(),
+        r => unimplemented!("expected rule 'hp_base' but found {:?}", r),
+    }
+}
+
 /// Parses a `GeomPiece` that represents a "ranged segment", that is a
 /// barcode, umi, read string, or discard segment having a ranged length.
 fn parse_ranged_segment(r: pest::iterators::Pair<Rule>) -> GeomPiece {
@@ -182,13 +762,35 @@ fn parse_ranged_segment(r: pest::iterators::Pair<Rule>) -> GeomPiece {
             let gl = parse_ranged_len(&mut r.into_inner());
             GeomPiece::Barcode(gl)
         }
+        Rule::ranged_sample_barcode_segment => {
+            let gl = parse_ranged_len(&mut r.into_inner());
+            GeomPiece::SampleBarcode(gl)
+        }
         Rule::ranged_discard_segment => {
             let gl = parse_ranged_len(&mut r.into_inner());
             GeomPiece::Discard(gl)
         }
         Rule::ranged_read_segment => {
+            let mut ri = r.into_inner();
+            let id = parse_read_pair_id(&mut ri);
+            let gl = parse_ranged_len(&mut ri);
+            GeomPiece::ReadSeq(gl, id)
+        }
+        Rule::ranged_tag_segment => {
+            let mut ri = r.into_inner();
+            let name = parse_tag_name(&mut ri);
+            let gl = parse_ranged_len(&mut ri);
+            GeomPiece::Tag(name, gl)
+        }
+        Rule::ranged_homopolymer_segment => {
+            let mut ri = r.into_inner();
+            let base = parse_hp_base(&mut ri);
+            let gl = parse_ranged_len(&mut ri);
+            GeomPiece::Homopolymer(base, gl)
+        }
+        Rule::ranged_spacer_segment => {
             let gl = parse_ranged_len(&mut r.into_inner());
-            GeomPiece::ReadSeq(gl)
+            GeomPiece::Spacer(gl)
         }
         _ => unimplemented!(),
     }
@@ -206,21 +808,45 @@ fn parse_fixed_segment(r: pest::iterators::Pair<Rule>) -> GeomPiece {
             let gl = parse_fixed_len(&mut r.into_inner());
             GeomPiece::Barcode(gl)
         }
+        Rule::fixed_sample_barcode_segment => {
+            let gl = parse_fixed_len(&mut r.into_inner());
+            GeomPiece::SampleBarcode(gl)
+        }
         Rule::fixed_discard_segment => {
             let gl = parse_fixed_len(&mut r.into_inner());
             GeomPiece::Discard(gl)
         }
         Rule::fixed_read_segment => {
+            let mut ri = r.into_inner();
+            let id = parse_read_pair_id(&mut ri);
+            let gl = parse_fixed_len(&mut ri);
+            GeomPiece::ReadSeq(gl, id)
+        }
+        Rule::fixed_tag_segment => {
+            let mut ri = r.into_inner();
+            let name = parse_tag_name(&mut ri);
+            let gl = parse_fixed_len(&mut ri);
+            GeomPiece::Tag(name, gl)
+        }
+        Rule::fixed_homopolymer_segment => {
+            let mut ri = r.into_inner();
+            let base = parse_hp_base(&mut ri);
+            let gl = parse_fixed_len(&mut ri);
+            GeomPiece::Homopolymer(base, gl)
+        }
+        Rule::fixed_spacer_segment => {
             let gl = parse_fixed_len(&mut r.into_inner());
-            GeomPiece::ReadSeq(gl)
+            GeomPiece::Spacer(gl)
         }
         // this should no longer be possible
         // under the new grammar (i.e. a fixed_seq_segment
         // is not a type of fixed_segment).
         /*
         Rule::fixed_seq_segment => {
-            let fseq = parse_fixed_seq(&mut r.into_inner());
-            GeomPiece::Fixed(fseq)
+            let mut inner = r.into_inner();
+            let fseq = parse_fixed_seq(&mut inner);
+            let max_mismatch = parse_max_mismatch(&mut inner);
+            GeomPiece::Fixed(fseq, max_mismatch)
         }
         */
         _ => unimplemented!(),
@@ -234,8 +860,20 @@ fn parse_unbounded_segment(r: pest::iterators::Pair<Rule>) -> GeomPiece {
     match r.as_rule() {
         Rule::unbounded_umi_segment => GeomPiece::Umi(GeomLen::Unbounded),
         Rule::unbounded_barcode_segment => GeomPiece::Barcode(GeomLen::Unbounded),
+        Rule::unbounded_sample_barcode_segment => GeomPiece::SampleBarcode(GeomLen::Unbounded),
         Rule::unbounded_discard_segment => GeomPiece::Discard(GeomLen::Unbounded),
-        Rule::unbounded_read_segment => GeomPiece::ReadSeq(GeomLen::Unbounded),
+        Rule::unbounded_read_segment => {
+            GeomPiece::ReadSeq(GeomLen::Unbounded, parse_read_pair_id(&mut r.into_inner()))
+        }
+        Rule::unbounded_tag_segment => {
+            let name = parse_tag_name(&mut r.into_inner());
+            GeomPiece::Tag(name, GeomLen::Unbounded)
+        }
+        Rule::unbounded_homopolymer_segment => {
+            let base = parse_hp_base(&mut r.into_inner());
+            GeomPiece::Homopolymer(base, GeomLen::Unbounded)
+        }
+        Rule::unbounded_spacer_segment => GeomPiece::Spacer(GeomLen::Unbounded),
         _ => unimplemented!(),
     }
 }
@@ -248,8 +886,10 @@ pub fn parse_segment(r: pest::iterators::Pair<Rule>) -> GeomPiece {
     match r.as_rule() {
         Rule::fixed_segment => parse_fixed_segment(r.into_inner().next().unwrap()),
         Rule::fixed_seq_segment => {
-            let fseq = parse_fixed_seq(&mut r.into_inner());
-            GeomPiece::Fixed(fseq)
+            let mut inner = r.into_inner();
+            let fseq = parse_fixed_seq(&mut inner);
+            let max_mismatch = parse_max_mismatch(&mut inner);
+            GeomPiece::Fixed(fseq, max_mismatch)
         }
         Rule::ranged_segment => parse_ranged_segment(r.into_inner().next().unwrap()),
         Rule::unbounded_segment => parse_unbounded_segment(r.into_inner().next().unwrap()),
@@ -258,36 +898,85 @@ pub fn parse_segment(r: pest::iterators::Pair<Rule>) -> GeomPiece {
 }
 
 /// This trait says that a given implementor is able to properly add itself
-/// to the command represented by `cmd`.
+/// to the command represented by `cmd`.  Implementors provide the arguments
+/// with [`AppendToCmdArgs::as_args`], which `append` adds to the command.
 pub trait AppendToCmdArgs {
-    fn append(&self, cmd: &mut std::process::Command);
+    /// Returns the arguments that [`AppendToCmdArgs::append`] adds to a command,
+    /// without requiring the caller to construct one.
+    fn as_args(&self) -> Vec<String>;
+
+    fn append(&self, cmd: &mut std::process::Command) {
+        cmd.args(self.as_args());
+    }
+}
+
+/// This trait allows an implementor to walk the pieces of a `FragmentGeomDesc`
+/// (see [`FragmentGeomDesc::accept`]).  Each method is called, in order, for each
+/// piece of the corresponding type, along with the number of the read containing it.
+/// All methods do nothing by default, so implementors need only provide those for
+/// the piece types they care about.
+pub trait GeomVisitor {
+    fn visit_barcode(&mut self, _read: u8, _len: &GeomLen) {}
+    fn visit_sample_barcode(&mut self, _read: u8, _len: &GeomLen) {}
+    fn visit_umi(&mut self, _read: u8, _len: &GeomLen) {}
+    fn visit_discard(&mut self, _read: u8, _len: &GeomLen) {}
+    fn visit_readseq(&mut self, _read: u8, _len: &GeomLen) {}
+    fn visit_fixed(&mut self, _read: u8, _seq: &NucStr, _max_mismatch: u32) {}
+    fn visit_tag(&mut self, _read: u8, _name: &str, _len: &GeomLen) {}
+    fn visit_homopolymer(&mut self, _read: u8, _base: u8, _len: &GeomLen) {}
+    fn visit_spacer(&mut self, _read: u8, _len: &GeomLen) {}
 }
 
 // ======== for piscem
 
 /// This struct holds a [`piscem`](https://github.com/COMBINE-lab/piscem) compatible
 /// description of the fragment geometry specification.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct PiscemGeomDesc {
     /// The `piscem` format specification for read 1.
     pub read1_desc: String,
-    /// The `piscem` format specification for read 2.
+    /// The `piscem` format specification for read 2.  This is empty for a
+    /// single-end fragment (i.e. one having no read 2 pieces).
     pub read2_desc: String,
 }
 
+impl fmt::Display for PiscemGeomDesc {
+    /// Renders the full `piscem` `--geometry` string, i.e. `1{...}2{...}`, or just
+    /// `1{...}` for a single-end fragment.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "1{}", self.read1_desc)?;
+        if !self.read2_desc.is_empty() {
+            write!(f, "2{}", self.read2_desc)?;
+        }
+        Ok(())
+    }
+}
+
 impl AppendToCmdArgs for PiscemGeomDesc {
-    /// Adds this `piscem` format geometry specification to the command
-    /// given by `cmd`.
-    fn append(&self, cmd: &mut std::process::Command) {
-        let geo_desc = format!("1{}2{}", self.read1_desc, self.read2_desc);
-        cmd.args(["--geometry", geo_desc.as_str()]);
+    /// Returns the arguments that add this `piscem` format geometry specification
+    /// to a command.
+    fn as_args(&self) -> Vec<String> {
+        vec!["--geometry".to_string(), format!("{}", self)]
     }
 }
 
+/// `piscem` has no notion of custom tags or spacers, so these are rendered as
+/// discarded sequence of the same length.  Unlike `salmon`, `piscem` accepts
+/// fixed sequence anchors containing IUPAC ambiguity codes, so these are passed
+/// through unchanged.
 fn as_piscem_geom_desc_single_read(geom_pieces: &[GeomPiece]) -> String {
     let desc = geom_pieces
         .iter()
-        .map(|x| format!("{}", x))
+        .map(|x| match x {
+            GeomPiece::Tag(_, gl) | GeomPiece::Homopolymer(_, gl) | GeomPiece::Spacer(gl) => {
+                format!("{}", GeomPiece::Discard(*gl))
+            }
+            // piscem has no notion of mismatch tolerance, so it is dropped
+            GeomPiece::Fixed(ns, _) => format!("{}", GeomPiece::Fixed(ns.clone(), 0)),
+            // nor of read pairing ids
+            GeomPiece::ReadSeq(gl, _) => format!("{}", GeomPiece::ReadSeq(*gl, None)),
+            x => format!("{}", x),
+        })
         .collect::<Vec<String>>()
         .join("");
     format!("{{{}}}", desc)
@@ -299,19 +988,31 @@ impl PiscemGeomDesc {
     /// and a slice of the `GeomPiece`s for read 2 (`geom_pieces_r2`).
     pub fn from_geom_pieces(geom_pieces_r1: &[GeomPiece], geom_pieces_r2: &[GeomPiece]) -> Self {
         let read1_desc = as_piscem_geom_desc_single_read(geom_pieces_r1);
-        let read2_desc = as_piscem_geom_desc_single_read(geom_pieces_r2);
+        let read2_desc = if geom_pieces_r2.is_empty() {
+            String::new()
+        } else {
+            as_piscem_geom_desc_single_read(geom_pieces_r2)
+        };
         Self {
             read1_desc,
             read2_desc,
         }
     }
+
+    /// Builds the `piscem` format descriptor for the fragment geometry `desc`, from the
+    /// pieces of its read 1 and read 2.  Since `piscem` can't represent fragments with
+    /// more than 2 reads, this returns `FragmentGeomError::TooManyReads` for them.
+    pub fn from_fragment(desc: &FragmentGeomDesc) -> Result<Self, FragmentGeomError> {
+        let (read1_desc, read2_desc) = desc.read_pair()?;
+        Ok(Self::from_geom_pieces(read1_desc, read2_desc))
+    }
 }
 
 // ======== for salmon
 
 /// This struct holds a [`salmon`](https://github.com/COMBINE-lab/salmon) compatible
 /// description of the fragment geometry specification.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct SalmonSeparateGeomDesc {
     pub barcode_desc: String,
     pub umi_desc: String,
@@ -319,298 +1020,3557 @@ pub struct SalmonSeparateGeomDesc {
 }
 
 impl AppendToCmdArgs for SalmonSeparateGeomDesc {
-    /// Given the `salmon` compatible geometry description, append this description
-    /// to the command `cmd`, assumed to be an invocation of `salmon alevin`.
-    fn append(&self, cmd: &mut std::process::Command) {
-        cmd.args([
-            "--read-geometry",
-            self.read_desc.as_str(),
-            "--bc-geometry",
-            self.barcode_desc.as_str(),
-            "--umi-geometry",
-            self.umi_desc.as_str(),
-        ]);
-    }
-}
-
-// for the "separate" salmon format, we need to collect
-// the intervals corresponding to each part of the geometry
-// separately.  So we need to keep track of intervals which
-// is just a pair of offsets.
-
-// the offset can be bounded, or unbounded
-// (i.e. goes until the end of the current read)
-enum GeomOffset {
-    Bounded(u32),
-    Unbounded,
+    /// Returns the arguments that add this `salmon` compatible geometry description
+    /// to a command, assumed to be an invocation of `salmon alevin`.
+    fn as_args(&self) -> Vec<String> {
+        vec![
+            "--read-geometry".to_string(),
+            self.read_desc.clone(),
+            "--bc-geometry".to_string(),
+            self.barcode_desc.clone(),
+            "--umi-geometry".to_string(),
+            self.umi_desc.clone(),
+        ]
+    }
 }
 
-// an interval is just a pair of offsets
-struct GeomInterval {
-    start: GeomOffset,
-    end: GeomOffset,
+/// Whether the start of an interval is counted from 0 or from 1.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum CoordBase {
+    /// The first base of a read is at position 0.
+    Zero,
+    /// The first base of a read is at position 1.
+    #[default]
+    One,
 }
 
-/// to be able to render a GeomInterval as a string. This
-/// is basically just rendering "x-y", for offsets x and y, but
-/// if y is unbounded, we render "x-end" instead.
-impl fmt::Display for GeomInterval {
-    // This trait requires `fmt` with this exact signature.
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        // Write strictly the first element into the supplied output
-        // stream: `f`. Returns `fmt::Result` which indicates whether the
-        // operation succeeded or failed. Note that `write!` uses syntax which
-        // is very similar to `println!`.
-        let s = match self.start {
-            GeomOffset::Bounded(x) => format!("{}", x),
-            _ => "XXX".to_string(),
-        };
-        let e = match self.end {
-            GeomOffset::Bounded(x) => format!("{}", x),
-            GeomOffset::Unbounded => "end".to_string(),
-        };
-        write!(f, "{}-{}", s, e)
-    }
+/// Whether the end of an interval is the position of its last base (inclusive),
+/// or the position just past it (exclusive).
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum EndKind {
+    #[default]
+    Inclusive,
+    Exclusive,
 }
 
-/// should return struct or enum instead
-fn as_salmon_desc_separate_helper(geom_pieces: &[GeomPiece]) -> (String, String, String) {
-    let mut offset = 0_u32;
+/// The coordinate system in which intervals are reported.  The default, used by
+/// `salmon`, is 1-based and inclusive, while e.g. `Coordinates { base: CoordBase::Zero,
+/// end: EndKind::Exclusive }` gives 0-based half-open intervals.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Coordinates {
+    pub base: CoordBase,
+    pub end: EndKind,
+}
 
-    let mut bc_intervals = Vec::<GeomInterval>::new();
-    let mut umi_intervals = Vec::<GeomInterval>::new();
-    let mut read_intervals = Vec::<GeomInterval>::new();
+/// The intervals occupied by each kind of tagged sequence within a single read.
+/// Each interval is a `(start, end)` pair in the `Coordinates` with which it was
+/// computed (1-based inclusive by default), where an `end` of `None` means that the
+/// interval runs until the end of the read.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct GeomIntervals {
+    /// The intervals of the barcode segments, in left-to-right order.
+    pub barcode: Vec<(u32, Option<u32>)>,
+    /// The intervals of the UMI segments, in left-to-right order.
+    pub umi: Vec<(u32, Option<u32>)>,
+    /// The intervals of the sample barcode segments, in left-to-right order.
+    pub sample_barcode: Vec<(u32, Option<u32>)>,
+    /// The names and intervals of the custom tagged segments, in left-to-right order.
+    pub tag: Vec<(String, (u32, Option<u32>))>,
+    /// The intervals of the biological read segments, in left-to-right order.
+    pub read: Vec<(u32, Option<u32>)>,
+    /// The intervals of the spacer segments, in left-to-right order.
+    pub spacer: Vec<(u32, Option<u32>)>,
+}
 
-    let append_interval_bounded = |offset: &mut u32, x: u32, intervals: &mut Vec<GeomInterval>| {
-        let start = *offset + 1;
-        let end = *offset + x;
-        intervals.push(GeomInterval {
-            start: GeomOffset::Bounded(start),
-            end: GeomOffset::Bounded(end),
-        });
-        *offset += x;
-    };
+/// Computes the 1-based inclusive intervals of the barcode, UMI, sample barcode, read, tag, and spacer segments
+/// of the read described by `geom_pieces`.  Discard segments and fixed sequence anchors
+/// consume bases, but are not themselves reported.  Because the offset of any piece
+/// following a ranged or unbounded piece can't be determined, an `anyhow::Error` is
+/// returned if such a piece would need to be assigned an interval.  An `anyhow::Error`
+/// wrapping `FragmentGeomError::OffsetOverflow` is returned if an offset would exceed
+/// `u32::MAX`.
+pub fn geom_piece_intervals(geom_pieces: &[GeomPiece]) -> Result<GeomIntervals> {
+    geom_piece_intervals_with_coords(geom_pieces, Coordinates::default())
+}
 
-    let append_interval_unbounded = |offset: &mut u32, intervals: &mut Vec<GeomInterval>| {
-        let start = *offset + 1;
-        intervals.push(GeomInterval {
-            start: GeomOffset::Bounded(start),
-            end: GeomOffset::Unbounded,
-        });
+/// Computes the intervals of the pieces of the read described by `geom_pieces` exactly as
+/// [`geom_piece_intervals`] does, but reports them in the coordinate system `coords`
+/// (e.g. the segment `b[16]` at the start of a read is `(1, Some(16))` in the default
+/// 1-based inclusive coordinates, and `(0, Some(16))` in 0-based half-open coordinates).
+pub fn geom_piece_intervals_with_coords(
+    geom_pieces: &[GeomPiece],
+    coords: Coordinates,
+) -> Result<GeomIntervals> {
+    let base_shift = match coords.base {
+        CoordBase::Zero => 0,
+        CoordBase::One => 1,
     };
+    let end_shift = match coords.end {
+        EndKind::Inclusive => 1,
+        EndKind::Exclusive => 0,
+    };
+    let mut intervals = GeomIntervals::default();
+    // the current 0-based offset, or `None` if it is
+    // no longer determinate.
+    let mut offset = Some(0_u32);
 
     for gp in geom_pieces {
-        match gp {
-            GeomPiece::Barcode(GeomLen::FixedLen(x)) => {
-                append_interval_bounded(&mut offset, *x, &mut bc_intervals);
-            }
-            GeomPiece::Umi(GeomLen::FixedLen(x)) => {
-                append_interval_bounded(&mut offset, *x, &mut umi_intervals);
-            }
-            GeomPiece::ReadSeq(GeomLen::FixedLen(x)) => {
-                append_interval_bounded(&mut offset, *x, &mut read_intervals);
-            }
-            GeomPiece::Discard(GeomLen::FixedLen(x)) => {
-                offset += x;
-            }
-            GeomPiece::Fixed(NucStr::Seq(_s)) => {
-                unimplemented!("Fixed content nucleotide tags are not supported in the salmon separate description format");
+        let o = match offset {
+            Some(o) => o,
+            None => bail!(
+                "The offset of {} can't be determined, as it follows a piece of variable length.",
+                gp
+            ),
+        };
+        let overflow = || FragmentGeomError::OffsetOverflow {
+            segment: gp.to_string(),
+        };
+        let gl = match gp {
+            GeomPiece::Fixed(NucStr::Seq(s), _) => {
+                let len = u32::try_from(s.len()).map_err(|_| overflow())?;
+                offset = Some(o.checked_add(len).ok_or_else(overflow)?);
+                continue;
             }
-            GeomPiece::Barcode(GeomLen::Unbounded) => {
-                append_interval_unbounded(&mut offset, &mut bc_intervals);
+            GeomPiece::Barcode(gl)
+            | GeomPiece::Umi(gl)
+            | GeomPiece::SampleBarcode(gl)
+            | GeomPiece::ReadSeq(gl, _)
+            | GeomPiece::Discard(gl)
+            | GeomPiece::Tag(_, gl)
+            | GeomPiece::Homopolymer(_, gl)
+            | GeomPiece::Spacer(gl) => gl,
+        };
+        let start = o.checked_add(base_shift).ok_or_else(overflow)?;
+        let iv = match gl {
+            GeomLen::FixedLen(x) => {
+                let next = o.checked_add(*x).ok_or_else(overflow)?;
+                // the 0-based inclusive end of an empty piece at the start of the read
+                // would be -1.
+                let end = next
+                    .checked_add(base_shift)
+                    .ok_or_else(overflow)?
+                    .checked_sub(end_shift)
+                    .ok_or_else(|| FragmentGeomError::ZeroLength {
+                        segment: gp.to_string(),
+                    })?;
+                offset = Some(next);
+                (start, Some(end))
             }
-            GeomPiece::Umi(GeomLen::Unbounded) => {
-                append_interval_unbounded(&mut offset, &mut umi_intervals);
+            GeomLen::Unbounded => {
+                offset = None;
+                (start, None)
             }
-            GeomPiece::ReadSeq(GeomLen::Unbounded) => {
-                append_interval_unbounded(&mut offset, &mut read_intervals);
+            GeomLen::LenRange(_, _) | GeomLen::LenRangeFrom(_) => {
+                bail!(
+                    "The interval of {} can't be determined, as it has a variable length.",
+                    gp
+                )
             }
-            GeomPiece::Discard(GeomLen::Unbounded) => {}
-            r => unimplemented!("encountered unexpected GeomPiece {:?}", r),
         };
+        match gp {
+            GeomPiece::Barcode(_) => intervals.barcode.push(iv),
+            GeomPiece::Umi(_) => intervals.umi.push(iv),
+            GeomPiece::SampleBarcode(_) => intervals.sample_barcode.push(iv),
+            GeomPiece::ReadSeq(_, _) => intervals.read.push(iv),
+            GeomPiece::Tag(name, _) => intervals.tag.push((name.clone(), iv)),
+            GeomPiece::Spacer(_) => intervals.spacer.push(iv),
+            GeomPiece::Discard(_) | GeomPiece::Fixed(_, _) | GeomPiece::Homopolymer(_, _) => {}
+        }
     }
+    Ok(intervals)
+}
 
-    let bc_str = bc_intervals
+/// Renders a list of intervals in the `salmon` separate format, i.e. "[x-y,...]"
+/// where an unbounded end is rendered as "end".
+fn as_salmon_interval_list(intervals: &[(u32, Option<u32>)]) -> String {
+    let ivs = intervals
         .iter()
-        .map(|x| format!("{}", x))
+        .map(|(s, e)| match e {
+            Some(e) => format!("{}-{}", s, e),
+            None => format!("{}-end", s),
+        })
         .collect::<Vec<String>>()
         .join(",");
+    format!("[{}]", ivs)
+}
 
-    let umi_str = umi_intervals
+/// Returns the barcode, UMI, and read interval lists of a single read (having number
+/// `read_num`) in the `salmon` separate format, each prefixed with `read_num`, or empty
+/// if the read has no such intervals.  Since `salmon` can't match a fixed sequence anchor, nor represent
+/// a sample barcode, a read containing either results in an `anyhow::Error`, as does one
+/// whose intervals can't be determined (see [`geom_piece_intervals`]).
+fn as_salmon_desc_separate_helper(
+    geom_pieces: &[GeomPiece],
+    read_num: u8,
+    coords: Coordinates,
+) -> Result<(String, String, String)> {
+    if let Some(gp) = geom_pieces
         .iter()
-        .map(|x| format!("{}", x))
-        .collect::<Vec<String>>()
-        .join(",");
-
-    let read_str = read_intervals
+        .find(|gp| matches!(gp, GeomPiece::Fixed(_, _)))
+    {
+        bail!(
+            "The fixed sequence anchor {} is not supported in the salmon separate description format",
+            gp
+        );
+    }
+    if geom_pieces
         .iter()
-        .map(|x| format!("{}", x))
-        .collect::<Vec<String>>()
-        .join(",");
-    (
-        format!("[{}]", bc_str),
-        format!("[{}]", umi_str),
-        format!("[{}]", read_str),
-    )
+        .any(|gp| matches!(gp, GeomPiece::SampleBarcode(_)))
+    {
+        bail!("Sample barcodes are not supported in the salmon separate description format");
+    }
+
+    let intervals = geom_piece_intervals_with_coords(geom_pieces, coords)?;
+
+    let with_read_num = |ivs: &[(u32, Option<u32>)]| {
+        if ivs.is_empty() {
+            String::new()
+        } else {
+            format!("{}{}", read_num, as_salmon_interval_list(ivs))
+        }
+    };
+    Ok((
+        with_read_num(&intervals.barcode),
+        with_read_num(&intervals.umi),
+        with_read_num(&intervals.read),
+    ))
 }
 
 impl SalmonSeparateGeomDesc {
-    pub fn from_geom_pieces(geom_pieces_r1: &[GeomPiece], geom_pieces_r2: &[GeomPiece]) -> Self {
-        let mut barcode_rep = String::new();
-        let mut umi_rep = String::new();
-        let mut read_rep = String::new();
-        let (bcp, up, rp) = as_salmon_desc_separate_helper(geom_pieces_r1);
-        if bcp != "[]" {
-            barcode_rep += &format!("1{}", bcp);
-        }
-        if up != "[]" {
-            umi_rep += &format!("1{}", up);
-        }
-        if rp != "[]" {
-            read_rep += &format!("1{}", rp);
-        }
+    /// This constructor builds the `salmon` separate format descriptor for this fragment
+    /// library from a slice of the constituent `GeomPiece`s for read 1 (`geom_pieces_r1`)
+    /// and a slice of the `GeomPiece`s for read 2 (`geom_pieces_r2`).  Geometries that
+    /// `salmon` can't express, such as those containing fixed sequence anchors, sample
+    /// barcodes, or variable-length segments before other segments, result in an
+    /// `anyhow::Error`.
+    pub fn from_geom_pieces(
+        geom_pieces_r1: &[GeomPiece],
+        geom_pieces_r2: &[GeomPiece],
+    ) -> Result<Self> {
+        Self::from_geom_pieces_with_coords(geom_pieces_r1, geom_pieces_r2, Coordinates::default())
+    }
 
-        let (bcp, up, rp) = as_salmon_desc_separate_helper(geom_pieces_r2);
-        if bcp != "[]" {
-            barcode_rep += &format!("2{}", bcp);
-        }
-        if up != "[]" {
-            umi_rep += &format!("2{}", up);
+    /// Builds the separate description of the given reads as `from_geom_pieces` does, but
+    /// with its intervals reported in the coordinate system `coords` rather than in the
+    /// 1-based inclusive coordinates that `salmon` expects.
+    pub fn from_geom_pieces_with_coords(
+        geom_pieces_r1: &[GeomPiece],
+        geom_pieces_r2: &[GeomPiece],
+        coords: Coordinates,
+    ) -> Result<Self> {
+        let (bc1, umi1, read1) = as_salmon_desc_separate_helper(geom_pieces_r1, 1, coords)?;
+        let (bc2, umi2, read2) = as_salmon_desc_separate_helper(geom_pieces_r2, 2, coords)?;
+
+        Ok(Self {
+            barcode_desc: bc1 + &bc2,
+            umi_desc: umi1 + &umi2,
+            read_desc: read1 + &read2,
+        })
+    }
+
+    /// Builds the `salmon` separate format descriptor for the fragment geometry `desc`,
+    /// from the pieces of its read 1 and read 2.  In addition to the geometries rejected
+    /// by [`SalmonSeparateGeomDesc::from_geom_pieces`], fragments with more than 2 reads
+    /// result in an `anyhow::Error` wrapping `FragmentGeomError::TooManyReads`.
+    pub fn from_fragment(desc: &FragmentGeomDesc) -> Result<Self> {
+        let (read1_desc, read2_desc) = desc.read_pair()?;
+        Self::from_geom_pieces(read1_desc, read2_desc)
+    }
+
+    /// Returns the barcode, UMI, and read interval lists (in that order) of the single
+    /// read described by `geom_pieces`, in the `salmon` separate format and prefixed
+    /// with `read_num`; e.g. `("1[1-16]", "1[17-28]", "")` for read 1 of
+    /// `1{b[16]u[12]x:}2{r:}`.  A list is empty if the read has no intervals of that
+    /// kind.  This allows the `salmon` arguments to be built up one read at a time, and
+    /// fails in the same cases as [`SalmonSeparateGeomDesc::from_geom_pieces`] (e.g. on a
+    /// fixed sequence anchor).
+    pub fn intervals_for_read(
+        geom_pieces: &[GeomPiece],
+        read_num: u8,
+    ) -> Result<(String, String, String)> {
+        as_salmon_desc_separate_helper(geom_pieces, read_num, Coordinates::default())
+    }
+}
+
+// ======== for STARsolo
+
+/// This struct holds a [`STARsolo`](https://github.com/alexdobin/STAR/blob/master/docs/STARsolo.md)
+/// compatible description of the fragment geometry specification, for use with
+/// `--soloType CB_UMI_Simple`.  The offsets are 1-based positions on read 1.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct StarsoloGeomDesc {
+    /// The 1-based start of the cellular barcode on read 1.
+    pub cb_start: u32,
+    /// The length of the cellular barcode.
+    pub cb_len: u32,
+    /// The 1-based start of the UMI on read 1.
+    pub umi_start: u32,
+    /// The length of the UMI.
+    pub umi_len: u32,
+}
+
+impl AppendToCmdArgs for StarsoloGeomDesc {
+    /// Returns the arguments that add this `STARsolo` compatible geometry description
+    /// to a command, assumed to be an invocation of `STAR`.
+    fn as_args(&self) -> Vec<String> {
+        vec![
+            "--soloType".to_string(),
+            "CB_UMI_Simple".to_string(),
+            "--soloCBstart".to_string(),
+            self.cb_start.to_string(),
+            "--soloCBlen".to_string(),
+            self.cb_len.to_string(),
+            "--soloUMIstart".to_string(),
+            self.umi_start.to_string(),
+            "--soloUMIlen".to_string(),
+            self.umi_len.to_string(),
+        ]
+    }
+}
+
+impl StarsoloGeomDesc {
+    /// This constructor builds the `STARsolo` format descriptor for this fragment
+    /// library from a slice of the constituent `GeomPiece`s for read 1 (`geom_pieces_r1`)
+    /// and a slice of the `GeomPiece`s for read 2 (`geom_pieces_r2`).  `CB_UMI_Simple`
+    /// can only express a single fixed-length barcode and a single fixed-length UMI on
+    /// read 1, so any other geometry results in an `anyhow::Error`, as does an offset
+    /// exceeding `u32::MAX` (wrapping `FragmentGeomError::OffsetOverflow`).
+    pub fn from_geom_pieces(
+        geom_pieces_r1: &[GeomPiece],
+        geom_pieces_r2: &[GeomPiece],
+    ) -> Result<Self> {
+        let mut offset = 0_u32;
+        let mut cb = None;
+        let mut umi = None;
+
+        for gp in geom_pieces_r1 {
+            let overflow = || FragmentGeomError::OffsetOverflow {
+                segment: gp.to_string(),
+            };
+            match gp {
+                GeomPiece::Barcode(GeomLen::FixedLen(x)) => {
+                    if cb.is_some() {
+                        bail!("STARsolo CB_UMI_Simple cannot represent multiple barcode segments");
+                    }
+                    cb = Some((offset.checked_add(1).ok_or_else(overflow)?, *x));
+                    offset = offset.checked_add(*x).ok_or_else(overflow)?;
+                }
+                GeomPiece::Umi(GeomLen::FixedLen(x)) => {
+                    if umi.is_some() {
+                        bail!("STARsolo CB_UMI_Simple cannot represent multiple UMI segments");
+                    }
+                    umi = Some((offset.checked_add(1).ok_or_else(overflow)?, *x));
+                    offset = offset.checked_add(*x).ok_or_else(overflow)?;
+                }
+                GeomPiece::Discard(GeomLen::FixedLen(x))
+                | GeomPiece::Homopolymer(_, GeomLen::FixedLen(x))
+                | GeomPiece::Spacer(GeomLen::FixedLen(x)) => {
+                    offset = offset.checked_add(*x).ok_or_else(overflow)?;
+                }
+                GeomPiece::Discard(GeomLen::Unbounded)
+                | GeomPiece::Homopolymer(_, GeomLen::Unbounded)
+                | GeomPiece::Spacer(GeomLen::Unbounded) => {}
+                r => bail!(
+                    "STARsolo CB_UMI_Simple cannot represent the piece {} on read 1",
+                    r
+                ),
+            }
         }
-        if rp != "[]" {
-            read_rep += &format!("2{}", rp);
+
+        if let Some(r) = geom_pieces_r2
+            .iter()
+            .find(|gp| matches!(gp, GeomPiece::Barcode(_) | GeomPiece::Umi(_)))
+        {
+            bail!(
+                "STARsolo CB_UMI_Simple expects the barcode and UMI on read 1, but found {} on read 2",
+                r
+            );
         }
 
-        Self {
-            barcode_desc: barcode_rep,
-            umi_desc: umi_rep,
-            read_desc: read_rep,
+        match (cb, umi) {
+            (Some((cb_start, cb_len)), Some((umi_start, umi_len))) => Ok(Self {
+                cb_start,
+                cb_len,
+                umi_start,
+                umi_len,
+            }),
+            _ => bail!("STARsolo CB_UMI_Simple requires both a barcode and a UMI on read 1"),
         }
     }
 }
 
-/// This structure holds our representation of the parsed fragment
-/// geometry description.
-#[derive(Debug)]
-pub struct FragmentGeomDesc {
-    /// The sequence of `GeomPiece`s describing read 1 of this fragment in left-to-right order.
-    pub read1_desc: Vec<GeomPiece>,
-    /// The sequence of `GeomPiece`s describing read 2 of this fragment in left-to-right order.
-    pub read2_desc: Vec<GeomPiece>,
+// ======== for kallisto / bustools
+
+/// This struct holds a [`kallisto bus`](https://github.com/pachterlab/kallisto) compatible
+/// description of the fragment geometry specification.  Each field is a comma-separated
+/// list of `file,start,stop` triples, where `file` is the 0-based index of the read,
+/// `start` is the 0-based offset of the segment, and `stop` is the (exclusive) end of the
+/// segment, or `0` if the segment runs until the end of the read.  A field for which
+/// there are no segments is `-1`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct BustoolsGeomDesc {
+    pub barcode_desc: String,
+    pub umi_desc: String,
+    pub read_desc: String,
 }
 
-impl fmt::Display for FragmentGeomDesc {
-    /// Write back a geometry fragment specification as exactly
-    /// the type of string the parser should accept in the first place.
-    /// This is the canonical representation of the geometry.
+impl fmt::Display for BustoolsGeomDesc {
+    /// Renders the technology string expected by `kallisto bus -x`.
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let desc1 = self
-            .read1_desc
-            .iter()
-            .map(|x| format!("{}", x))
-            .collect::<Vec<String>>()
-            .join("");
-        let desc2 = self
-            .read2_desc
-            .iter()
-            .map(|x| format!("{}", x))
-            .collect::<Vec<String>>()
-            .join("");
-        write!(f, "1{{{}}}2{{{}}}", desc1, desc2)
+        write!(
+            f,
+            "{}:{}:{}",
+            self.barcode_desc, self.umi_desc, self.read_desc
+        )
     }
 }
 
-impl FragmentGeomDesc {
-    /// A "complex" geometry is one that contains
-    /// a FixedSeq piece, and/or a BoundedRange piece
-    pub fn is_complex_geometry(&self) -> bool {
-        for gp in self.read1_desc.iter().chain(self.read2_desc.iter()) {
-            if gp.is_complex() {
-                return true;
+impl AppendToCmdArgs for BustoolsGeomDesc {
+    /// Returns the arguments that add this `kallisto bus` compatible geometry
+    /// description to a command, assumed to be an invocation of `kallisto bus`.
+    fn as_args(&self) -> Vec<String> {
+        vec!["-x".to_string(), format!("{}", self)]
+    }
+}
+
+/// Collects the `file,start,stop` triples of the barcode, UMI, and read segments
+/// of a single read (having index `file_idx`) into `bc`, `umi` and `read` respectively.
+fn as_bustools_triples_single_read(
+    geom_pieces: &[GeomPiece],
+    file_idx: u32,
+    bc: &mut Vec<String>,
+    umi: &mut Vec<String>,
+    read: &mut Vec<String>,
+) -> Result<()> {
+    let mut offset = 0_u32;
+    for gp in geom_pieces {
+        let overflow = || FragmentGeomError::OffsetOverflow {
+            segment: gp.to_string(),
+        };
+        match gp {
+            GeomPiece::Barcode(GeomLen::FixedLen(x)) => {
+                let end = offset.checked_add(*x).ok_or_else(overflow)?;
+                bc.push(format!("{},{},{}", file_idx, offset, end));
+                offset = end;
+            }
+            GeomPiece::Umi(GeomLen::FixedLen(x)) => {
+                let end = offset.checked_add(*x).ok_or_else(overflow)?;
+                umi.push(format!("{},{},{}", file_idx, offset, end));
+                offset = end;
+            }
+            GeomPiece::ReadSeq(GeomLen::FixedLen(x), _) => {
+                let end = offset.checked_add(*x).ok_or_else(overflow)?;
+                read.push(format!("{},{},{}", file_idx, offset, end));
+                offset = end;
+            }
+            GeomPiece::Discard(GeomLen::FixedLen(x))
+            | GeomPiece::Homopolymer(_, GeomLen::FixedLen(x))
+            | GeomPiece::Spacer(GeomLen::FixedLen(x)) => {
+                offset = offset.checked_add(*x).ok_or_else(overflow)?;
+            }
+            GeomPiece::ReadSeq(GeomLen::Unbounded, _) => {
+                read.push(format!("{},{},0", file_idx, offset));
             }
+            GeomPiece::Discard(GeomLen::Unbounded)
+            | GeomPiece::Homopolymer(_, GeomLen::Unbounded)
+            | GeomPiece::Spacer(GeomLen::Unbounded) => {}
+            r => bail!(
+                "The kallisto bus technology string cannot represent the piece {}",
+                r
+            ),
         }
-        false
     }
+    Ok(())
+}
 
-    /// A "simple" geometry is one that contains only fixed length pieces
-    /// (but doesn't include any fixed seq segments) and unbounded pieces.
-    pub fn is_simple_geometry(&self) -> bool {
-        !self.is_complex_geometry()
+impl BustoolsGeomDesc {
+    /// This constructor builds the `kallisto bus` format descriptor for this fragment
+    /// library from a slice of the constituent `GeomPiece`s for read 1 (`geom_pieces_r1`)
+    /// and a slice of the `GeomPiece`s for read 2 (`geom_pieces_r2`).  Read 1 is mapped to
+    /// file index 0 and read 2 to file index 1.  Fixed sequence anchors, ranged lengths, and
+    /// unbounded barcodes or UMIs can't be represented and result in an `anyhow::Error`,
+    /// as does an offset exceeding `u32::MAX` (wrapping `FragmentGeomError::OffsetOverflow`).
+    pub fn from_geom_pieces(
+        geom_pieces_r1: &[GeomPiece],
+        geom_pieces_r2: &[GeomPiece],
+    ) -> Result<Self> {
+        let mut bc = Vec::<String>::new();
+        let mut umi = Vec::<String>::new();
+        let mut read = Vec::<String>::new();
+
+        as_bustools_triples_single_read(geom_pieces_r1, 0, &mut bc, &mut umi, &mut read)?;
+        as_bustools_triples_single_read(geom_pieces_r2, 1, &mut bc, &mut umi, &mut read)?;
+
+        if read.is_empty() {
+            bail!("The kallisto bus technology string requires at least one read segment");
+        }
+
+        let join_or_missing = |v: Vec<String>| {
+            if v.is_empty() {
+                "-1".to_string()
+            } else {
+                v.join(",")
+            }
+        };
+
+        Ok(Self {
+            barcode_desc: join_or_missing(bc),
+            umi_desc: join_or_missing(umi),
+            read_desc: read.join(","),
+        })
     }
 }
 
-/// Parse the description of a single read.  It's expected that this function is called
-/// when the enclosing rule matches a rule description.  In that case, this function is
-/// called with the `into_inner` of that Pair.  This function returns a vector containing
-/// the parsed geometry of the input description.
-fn parse_read_description(read_desc: pest::iterators::Pairs<Rule>) -> Vec<GeomPiece> {
-    let mut read_geom = Vec::<GeomPiece>::new();
-    for rd in read_desc {
-        match rd.as_rule() {
-            Rule::read_desc => {
-                for geom_piece in rd.into_inner() {
-                    read_geom.push(parse_segment(geom_piece));
-                }
+// ======== for UMI-tools
+
+/// This struct holds a [`UMI-tools`](https://github.com/CGATOxford/UMI-tools)
+/// compatible description of the fragment geometry specification, i.e. the string
+/// patterns passed to `umi_tools extract` with `--bc-pattern` (and `--bc-pattern2`),
+/// in which `C` denotes a cell barcode base, `N` a UMI base, and `X` a discarded base.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct UmiToolsGeomDesc {
+    /// The pattern for read 1.
+    pub bc_pattern: String,
+    /// The pattern for read 2, if read 2 contains any barcode, UMI, or discarded bases.
+    pub bc_pattern2: Option<String>,
+}
+
+impl AppendToCmdArgs for UmiToolsGeomDesc {
+    /// Returns the arguments that add this `UMI-tools` format geometry specification
+    /// to a command, assumed to be an invocation of `umi_tools extract`.
+    fn as_args(&self) -> Vec<String> {
+        let mut args = vec![format!("--bc-pattern={}", self.bc_pattern)];
+        if let Some(p) = &self.bc_pattern2 {
+            args.push(format!("--bc-pattern2={}", p));
+        }
+        args
+    }
+}
+
+/// Expands the pieces of a single read into a `UMI-tools` string pattern.  The pattern
+/// covers the leading fixed-length barcode, UMI, and discard pieces, and may only be
+/// followed by a single unbounded read or discard piece, which is left unmatched.
+fn as_umi_tools_pattern_single_read(geom_pieces: &[GeomPiece]) -> Result<String> {
+    let mut pattern = String::new();
+    for (i, gp) in geom_pieces.iter().enumerate() {
+        let (c, x) = match gp {
+            GeomPiece::Barcode(GeomLen::FixedLen(x)) => ('C', x),
+            GeomPiece::Umi(GeomLen::FixedLen(x)) => ('N', x),
+            GeomPiece::Discard(GeomLen::FixedLen(x))
+            | GeomPiece::Homopolymer(_, GeomLen::FixedLen(x))
+            | GeomPiece::Spacer(GeomLen::FixedLen(x)) => ('X', x),
+            GeomPiece::ReadSeq(GeomLen::Unbounded, _)
+            | GeomPiece::Discard(GeomLen::Unbounded)
+            | GeomPiece::Homopolymer(_, GeomLen::Unbounded)
+            | GeomPiece::Spacer(GeomLen::Unbounded)
+                if i + 1 == geom_pieces.len() =>
+            {
+                break;
             }
-            _ => unreachable!(),
+            r => bail!(
+                "A UMI-tools string pattern cannot represent the piece {}",
+                r
+            ),
         };
+        for _ in 0..*x {
+            pattern.push(c);
+        }
     }
-    read_geom
+    Ok(pattern)
 }
 
-impl<'a> TryFrom<&'a str> for FragmentGeomDesc {
-    type Error = anyhow::Error;
+impl UmiToolsGeomDesc {
+    /// This constructor builds the `UMI-tools` format descriptor for this fragment
+    /// library from a slice of the constituent `GeomPiece`s for read 1 (`geom_pieces_r1`)
+    /// and a slice of the `GeomPiece`s for read 2 (`geom_pieces_r2`).  The string pattern
+    /// can only express fixed-length pieces at the start of each read, so ranged pieces,
+    /// fixed sequence anchors, or any unbounded piece other than a final read or discard
+    /// piece result in an `anyhow::Error`.
+    pub fn from_geom_pieces(
+        geom_pieces_r1: &[GeomPiece],
+        geom_pieces_r2: &[GeomPiece],
+    ) -> Result<Self> {
+        let bc_pattern = as_umi_tools_pattern_single_read(geom_pieces_r1)?;
+        if bc_pattern.is_empty() {
+            bail!("A UMI-tools string pattern requires read 1 to start with fixed-length pieces");
+        }
+        let bc_pattern2 = as_umi_tools_pattern_single_read(geom_pieces_r2)?;
+        Ok(Self {
+            bc_pattern,
+            bc_pattern2: if bc_pattern2.is_empty() {
+                None
+            } else {
+                Some(bc_pattern2)
+            },
+        })
+    }
+}
 
-    /// This is the main entry point to obtain a `FragmentGeomDesc` structure.
-    /// This function parses the FGDL description string provided as `arg`, and
-    /// returns either `Ok(FragGeomDesc)`, if the parse is succesful or an
-    /// `anyhow::Error` if the parsing fails.
-    ///
-    /// Currently, the FGDL makes a structural assumption that is reflected in the
-    /// way this function works.  The description string will describe the fragment
-    /// geometry for a fragment consisting of a pair of reads (i.e. currently
-    /// there is no support for single-end reads or fragments containing > 2 reads).
-    fn try_from(arg: &'a str) -> Result<Self, Self::Error> {
-        match FragGeomParser::parse(Rule::frag_desc, arg) {
-            Ok(fragment_desc) => {
-                // Where we'll hold the `GeomPiece`s that constitute the
-                // parse of each read.
-                let mut r1_desc = None;
-                let mut r2_desc = None;
+// ======== for flexiplex
+
+/// This struct holds a [`flexiplex`](https://github.com/DavidsonGroup/flexiplex)
+/// compatible description of the fragment geometry specification; that is, the
+/// search pattern of a barcode, optionally followed by a UMI, and optionally
+/// flanked on either side by known sequence.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FlexiplexGeomDesc {
+    /// The known sequence preceding the barcode, if any.
+    pub left_flank: Option<String>,
+    /// The length of the barcode.
+    pub barcode_len: u32,
+    /// The length of the UMI following the barcode, if any.
+    pub umi_len: Option<u32>,
+    /// The known sequence following the barcode (and UMI), if any.
+    pub right_flank: Option<String>,
+}
+
+impl AppendToCmdArgs for FlexiplexGeomDesc {
+    /// Returns the arguments that add this search pattern to a command, assumed to be
+    /// an invocation of `flexiplex`, as the sequence of `-x` (flank), `-b` (barcode),
+    /// and `-u` (UMI) options that flexiplex searches for in order.
+    fn as_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(left_flank) = &self.left_flank {
+            args.extend(["-x".to_string(), left_flank.clone()]);
+        }
+        args.extend(["-b".to_string(), "?".repeat(self.barcode_len as usize)]);
+        if let Some(umi_len) = self.umi_len {
+            args.extend(["-u".to_string(), "?".repeat(umi_len as usize)]);
+        }
+        if let Some(right_flank) = &self.right_flank {
+            args.extend(["-x".to_string(), right_flank.clone()]);
+        }
+        args
+    }
+}
+
+/// Returns the known sequence of a piece that can serve as a flexiplex flank; that
+/// is, of a fixed sequence anchor or a fixed-length homopolymer.
+fn as_flexiplex_flank(gp: &GeomPiece) -> Option<String> {
+    match gp {
+        GeomPiece::Fixed(NucStr::Seq(s), _) => Some(s.clone()),
+        GeomPiece::Homopolymer(b, GeomLen::FixedLen(x)) => {
+            Some((*b as char).to_string().repeat(*x as usize))
+        }
+        _ => None,
+    }
+}
+
+impl FlexiplexGeomDesc {
+    /// This constructor builds the `flexiplex` search pattern from a slice of the
+    /// constituent `GeomPiece`s of the single read to be searched (`geom_pieces`), as
+    /// flexiplex searches each (typically long) read independently.  Ignoring any
+    /// leading and trailing discards (or spacers), the read must consist of an optional flank (a
+    /// fixed sequence anchor or fixed-length homopolymer), a fixed-length barcode, an
+    /// optional fixed-length UMI, and an optional flank.  In place of the barcode, a
+    /// fixed-length read segment may be given, to search for an unknown sequence (e.g.
+    /// a guide RNA) between known flanks.  Any other geometry results in an
+    /// `anyhow::Error`.
+    pub fn from_geom_pieces(geom_pieces: &[GeomPiece]) -> Result<Self> {
+        let mut rest = geom_pieces;
+        while let [GeomPiece::Discard(_) | GeomPiece::Spacer(_), tail @ ..] = rest {
+            rest = tail;
+        }
+        while let [head @ .., GeomPiece::Discard(_) | GeomPiece::Spacer(_)] = rest {
+            rest = head;
+        }
+
+        let mut pieces = rest.iter().peekable();
+        let left_flank = pieces.peek().and_then(|gp| as_flexiplex_flank(gp));
+        if left_flank.is_some() {
+            pieces.next();
+        }
+        let barcode_len = match pieces.next() {
+            Some(GeomPiece::Barcode(GeomLen::FixedLen(x)))
+            | Some(GeomPiece::ReadSeq(GeomLen::FixedLen(x), _)) => *x,
+            Some(gp) => bail!(
+                "flexiplex expects a fixed-length barcode after any left flank, but found {}",
+                gp
+            ),
+            None => bail!("flexiplex requires a fixed-length barcode"),
+        };
+        let umi_len = match pieces.peek() {
+            Some(GeomPiece::Umi(GeomLen::FixedLen(x))) => {
+                pieces.next();
+                Some(*x)
+            }
+            _ => None,
+        };
+        let right_flank = pieces.peek().and_then(|gp| as_flexiplex_flank(gp));
+        if right_flank.is_some() {
+            pieces.next();
+        }
+        if let Some(gp) = pieces.next() {
+            bail!(
+                "flexiplex can only search for a single barcode (and UMI) between two flanks, but found {}",
+                gp
+            );
+        }
+
+        Ok(Self {
+            left_flank,
+            barcode_len,
+            umi_len,
+            right_flank,
+        })
+    }
+}
+
+// ======== for Cell Ranger
+
+/// The location of a single segment in a `Cell Ranger` custom chemistry definition.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CellRangerReadSpan {
+    /// The read containing the segment (`"R1"` or `"R2"`).
+    pub read_type: &'static str,
+    /// The 0-based offset of the segment within the read.
+    pub offset: u32,
+    /// The length of the segment, or `None` if it extends to the end of the read.
+    pub length: Option<u32>,
+}
+
+impl CellRangerReadSpan {
+    /// Writes this span as a JSON object.
+    fn to_json(&self) -> String {
+        let length = match self.length {
+            Some(l) => l.to_string(),
+            None => "null".to_string(),
+        };
+        format!(
+            "{{\"read_type\":\"{}\",\"offset\":{},\"length\":{}}}",
+            self.read_type, self.offset, length
+        )
+    }
+}
+
+/// This struct holds a [`Cell Ranger`](https://www.10xgenomics.com/support/software/cell-ranger)
+/// compatible description of the fragment geometry specification, i.e. the location of
+/// the barcode, UMI, and RNA segments of a custom chemistry definition (as passed with
+/// `--chemistry`).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CellRangerGeomDesc {
+    /// The location of the cellular barcode.
+    pub barcode: CellRangerReadSpan,
+    /// The location of the UMI.
+    pub umi: CellRangerReadSpan,
+    /// The location of the biological (RNA) sequence.
+    pub rna: CellRangerReadSpan,
+}
+
+impl CellRangerGeomDesc {
+    /// This constructor builds the `Cell Ranger` format descriptor for this fragment
+    /// library from a slice of the constituent `GeomPiece`s for read 1 (`geom_pieces_r1`)
+    /// and a slice of the `GeomPiece`s for read 2 (`geom_pieces_r2`).  The locations are
+    /// computed with [`geom_piece_intervals`], as for the `salmon` exporter.  A custom
+    /// chemistry can only express a single barcode, UMI, and RNA segment, and has no
+    /// notion of fixed sequence anchors, sample barcodes, or custom tags, so any other
+    /// geometry results in an `anyhow::Error`.
+    pub fn from_geom_pieces(
+        geom_pieces_r1: &[GeomPiece],
+        geom_pieces_r2: &[GeomPiece],
+    ) -> Result<Self> {
+        let mut barcode = None;
+        let mut umi = None;
+        let mut rna = None;
+
+        for (read_type, geom_pieces) in [("R1", geom_pieces_r1), ("R2", geom_pieces_r2)] {
+            if let Some(gp) = geom_pieces.iter().find(|gp| {
+                matches!(
+                    gp,
+                    GeomPiece::Fixed(_, _) | GeomPiece::SampleBarcode(_) | GeomPiece::Tag(_, _)
+                )
+            }) {
+                bail!(
+                    "A Cell Ranger custom chemistry cannot represent the piece {}",
+                    gp
+                );
+            }
+
+            let intervals = geom_piece_intervals(geom_pieces)?;
+            for (name, ivs, dest) in [
+                ("barcode", &intervals.barcode, &mut barcode),
+                ("UMI", &intervals.umi, &mut umi),
+                ("RNA", &intervals.read, &mut rna),
+            ] {
+                for (start, end) in ivs {
+                    if dest.is_some() {
+                        bail!(
+                            "A Cell Ranger custom chemistry cannot represent multiple {} segments",
+                            name
+                        );
+                    }
+                    *dest = Some(CellRangerReadSpan {
+                        read_type,
+                        offset: start - 1,
+                        length: end.map(|e| e - start + 1),
+                    });
+                }
+            }
+        }
+
+        match (barcode, umi, rna) {
+            (Some(barcode), Some(umi), Some(rna)) => Ok(Self { barcode, umi, rna }),
+            _ => bail!(
+                "A Cell Ranger custom chemistry requires a barcode, a UMI, and an RNA segment"
+            ),
+        }
+    }
+
+    /// Returns the JSON object describing the locations of the barcode, UMI, and RNA
+    /// segments, in the layout of a `Cell Ranger` custom chemistry definition (where
+    /// the `barcode` and `umi` entries are lists).
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"barcode\":[{}],\"umi\":[{}],\"rna\":{}}}",
+            self.barcode.to_json(),
+            self.umi.to_json(),
+            self.rna.to_json()
+        )
+    }
+}
+
+/// The orientation in which a read is sequenced, relative to the orientation in which
+/// its pieces are described.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Orientation {
+    /// The read is sequenced in the described orientation.
+    #[default]
+    Forward,
+    /// The read is sequenced from the opposite strand, so tools must reverse
+    /// complement it; written as e.g. `2-{r:}`.
+    Reverse,
+}
+
+/// This structure holds our representation of the parsed fragment
+/// geometry description.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FragmentGeomDesc {
+    /// The sequences of `GeomPiece`s describing each read of this fragment in left-to-right
+    /// order; `reads[0]` describes read 1, `reads[1]` describes read 2, and so on.  A
+    /// single-end fragment has just one entry.
+    pub reads: Vec<Vec<GeomPiece>>,
+    /// The optional human-readable labels of the reads of this fragment, written after
+    /// a read block as e.g. `1{b[16]u[12]}:"cell barcode read"`; `read_labels[i]` is the
+    /// label of read `i+1`.  Trailing unlabeled reads have no entry, so this is empty
+    /// for a geometry without labels (see [`FragmentGeomDesc::read_label`]).  A label
+    /// may contain any character other than `"`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub read_labels: Vec<Option<String>>,
+    /// The optional declared sequencing lengths of the reads of this fragment, written
+    /// after the read number as e.g. `2@150{r:}`; `read_lens[i]` is the length of read
+    /// `i+1`.  As with `read_labels`, trailing reads without a length have no entry (see
+    /// [`FragmentGeomDesc::read_len_hint`]).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub read_lens: Vec<Option<u32>>,
+    /// The orientations of the reads of this fragment, where a reverse-oriented read is
+    /// marked after its read number as e.g. `2-{r:}`; `read_orientations[i]` is the
+    /// orientation of read `i+1`.  Trailing `Forward` reads have no entry, so this is
+    /// empty if every read is forward (see [`FragmentGeomDesc::read_orientation`]).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub read_orientations: Vec<Orientation>,
+    /// The optional name of this geometry (e.g. the chemistry it describes), written
+    /// as a prefix of the description, e.g. `10xv3:1{b[16]u[12]x:}2{r:}`.  A name may
+    /// contain ASCII letters, digits, `_`, `-`, and `.`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub name: Option<String>,
+    /// The sequence of `GeomPiece`s describing the first index read (I1) of this fragment
+    /// in left-to-right order.  This is empty if the fragment has no such index read.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub index1_desc: Vec<GeomPiece>,
+    /// The sequence of `GeomPiece`s describing the second index read (I2) of this fragment
+    /// in left-to-right order.  This is empty if the fragment has no such index read.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub index2_desc: Vec<GeomPiece>,
+}
+
+/// The location of a parsed piece within an FGDL description, as returned by
+/// [`FragmentGeomDesc::try_from_with_spans`]: the number of the read containing
+/// the piece, the 0-based index of the piece within that read, and the byte range
+/// of the piece within the description.
+pub type PieceSpan = (u8, usize, std::ops::Range<usize>);
+
+/// Options controlling how an FGDL description is parsed by
+/// [`FragmentGeomDesc::try_from_with_opts`].  The default options impose no
+/// limits beyond those of the grammar, and are those used by `try_from`.  When
+/// parsing untrusted input, e.g. `ParseOpts::new().with_max_fixed_seq_len(Some(64))`
+/// rejects descriptions with implausibly long fixed sequences.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ParseOpts {
+    max_fixed_seq_len: Option<usize>,
+    max_segment_len: Option<u32>,
+    alphabet: Alphabet,
+    u_to_t: bool,
+}
+
+/// The bases that may appear in a fixed sequence, as selected by
+/// [`ParseOpts::with_alphabet`].  The default is `Iupac`, which accepts every
+/// base the grammar does.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Alphabet {
+    /// `A`, `C`, `G`, and `T`.
+    Dna,
+    /// `A`, `C`, `G`, and `U`.
+    Rna,
+    /// Any IUPAC nucleotide code (including `U` and `N`).
+    #[default]
+    Iupac,
+}
+
+impl Alphabet {
+    /// Returns true if the (uppercase) base `base` belongs to this alphabet.
+    pub fn contains(&self, base: char) -> bool {
+        match self {
+            Alphabet::Dna => matches!(base, 'A' | 'C' | 'G' | 'T'),
+            Alphabet::Rna => matches!(base, 'A' | 'C' | 'G' | 'U'),
+            Alphabet::Iupac => "ACGTURYSWKMBDHVN".contains(base),
+        }
+    }
+}
+
+impl ParseOpts {
+    /// Creates a new set of options with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// If `max_fixed_seq_len` is `Some(n)`, any fixed sequence (in a read or index
+    /// read) longer than `n` bases results in `FragmentGeomError::FixedSeqTooLong`.
+    pub fn with_max_fixed_seq_len(mut self, max_fixed_seq_len: Option<usize>) -> Self {
+        self.max_fixed_seq_len = max_fixed_seq_len;
+        self
+    }
+
+    /// If `max_segment_len` is `Some(n)`, any segment (in a read or index read) whose
+    /// length, or the upper bound of whose range, is greater than `n` results in
+    /// `FragmentGeomError::SegmentTooLong`.  An open-ended range is checked against
+    /// its lower bound, and fixed sequences are limited by
+    /// [`ParseOpts::with_max_fixed_seq_len`] instead.
+    pub fn with_max_segment_len(mut self, max_segment_len: Option<u32>) -> Self {
+        self.max_segment_len = max_segment_len;
+        self
+    }
+
+    /// Any fixed sequence (in a read or index read) containing a base outside of
+    /// `alphabet` results in `FragmentGeomError::InvalidBase`; e.g. `Alphabet::Rna`
+    /// accepts the anchor `f[ACGU]`, but rejects `f[ACGT]`.
+    pub fn with_alphabet(mut self, alphabet: Alphabet) -> Self {
+        self.alphabet = alphabet;
+        self
+    }
+
+    /// If `u_to_t` is true, every `U` in a fixed sequence is rewritten as `T` once the
+    /// sequence has been checked against the alphabet, so that e.g. an RNA anchor
+    /// `f[ACGU]` is stored as `f[ACGT]`.
+    pub fn with_u_to_t(mut self, u_to_t: bool) -> Self {
+        self.u_to_t = u_to_t;
+        self
+    }
+}
+
+/// Renders a `FragmentGeomDesc` as a string, with a configurable layout.  The
+/// default configuration produces the compact, canonical form written by the
+/// `Display` impl of `FragmentGeomDesc` (e.g. `1{b[16]u[12]x:}2{r:}`), while e.g.
+/// `GeomDescFormatter::new().with_spaces(true)` produces the more readable
+/// `1{b[16] u[12] x:} 2{r:}`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct GeomDescFormatter {
+    spaces: bool,
+    read_numbers: bool,
+}
+
+impl Default for GeomDescFormatter {
+    fn default() -> Self {
+        Self {
+            spaces: false,
+            read_numbers: true,
+        }
+    }
+}
+
+impl GeomDescFormatter {
+    /// Creates a new formatter with the default (compact) configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// If `spaces` is true, pieces within a read, and consecutive read blocks,
+    /// are separated by a single space.
+    pub fn with_spaces(mut self, spaces: bool) -> Self {
+        self.spaces = spaces;
+        self
+    }
+
+    /// If `read_numbers` is false, the number preceding each read block is omitted
+    /// (e.g. `{b[16]u[12]x:}{r:}`).  The labels of index read blocks, and any read
+    /// orientation or declared read length (e.g. `{b[16]u[12]x:}-@150{r:}`), are always
+    /// written.
+    pub fn with_read_numbers(mut self, read_numbers: bool) -> Self {
+        self.read_numbers = read_numbers;
+        self
+    }
+
+    /// Returns the rendering of `desc` under this configuration.
+    pub fn format(&self, desc: &FragmentGeomDesc) -> String {
+        let mut out = String::new();
+        // writing to a `String` can't fail
+        self.write_to(&mut out, desc).unwrap();
+        out
+    }
+
+    /// Writes the rendering of `desc` under this configuration directly to `w`
+    /// (without building intermediate strings).
+    pub fn write_to<W: fmt::Write>(&self, w: &mut W, desc: &FragmentGeomDesc) -> fmt::Result {
+        if let Some(name) = &desc.name {
+            write!(w, "{}:", name)?;
+        }
+        let mut first = true;
+        for (i, read_desc) in desc.reads.iter().enumerate() {
+            self.write_block_sep(w, &mut first)?;
+            if self.read_numbers {
+                write!(w, "{}", i + 1)?;
+            }
+            if let Some(Orientation::Reverse) = desc.read_orientations.get(i) {
+                w.write_char('-')?;
+            }
+            if let Some(Some(read_len)) = desc.read_lens.get(i) {
+                write!(w, "@{}", read_len)?;
+            }
+            self.write_read_desc(w, read_desc)?;
+            if let Some(Some(label)) = desc.read_labels.get(i) {
+                write!(w, ":\"{}\"", label)?;
+            }
+        }
+        // index reads are only written when present.
+        for (label, index_desc) in [("i1", &desc.index1_desc), ("i2", &desc.index2_desc)] {
+            if !index_desc.is_empty() {
+                self.write_block_sep(w, &mut first)?;
+                w.write_str(label)?;
+                self.write_read_desc(w, index_desc)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_block_sep<W: fmt::Write>(&self, w: &mut W, first: &mut bool) -> fmt::Result {
+        if !*first && self.spaces {
+            w.write_char(' ')?;
+        }
+        *first = false;
+        Ok(())
+    }
+
+    /// Writes the pieces of a single read as a `{...}` block.
+    fn write_read_desc<W: fmt::Write>(&self, w: &mut W, geom_pieces: &[GeomPiece]) -> fmt::Result {
+        w.write_char('{')?;
+        for (i, gp) in geom_pieces.iter().enumerate() {
+            if i > 0 && self.spaces {
+                w.write_char(' ')?;
+            }
+            write!(w, "{}", gp)?;
+        }
+        w.write_char('}')
+    }
+}
+
+impl fmt::Display for FragmentGeomDesc {
+    /// Write back a geometry fragment specification as exactly
+    /// the type of string the parser should accept in the first place.
+    /// This is the canonical representation of the geometry.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        GeomDescFormatter::default().write_to(f, self)
+    }
+}
+
+/// A classification of a geometry by the kinds of pieces that make it hard to
+/// export, as returned by [`FragmentGeomDesc::complexity`].  Ranged lengths and
+/// fixed sequence anchors have different implications for exporters (the former
+/// make offsets indeterminate, while the latter require matching a sequence), so
+/// they are distinguished here rather than lumped together as "complex".
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GeomComplexity {
+    /// Every piece has a fixed length, and there are no anchors.
+    FullyFixed,
+    /// Every piece has a fixed length or is unbounded (e.g. `1{b[16]u[12]x:}2{r:}`),
+    /// and there are no anchors.
+    FixedWithUnbounded,
+    /// Some piece has a ranged (or open-ended, e.g. `b[8-]`) length, and there are no
+    /// anchors.
+    HasRanges,
+    /// There is a fixed sequence anchor, but no ranged piece.
+    HasAnchors,
+    /// There is both a fixed sequence anchor and a ranged piece.
+    HasAnchorsAndRanges,
+}
+
+/// Well-known chemistries that downstream tools can refer to by name, rather
+/// than by an explicit geometry description.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum KnownChemistry {
+    /// 10x Chromium v2 (16bp barcode, 10bp UMI).
+    ChromiumV2,
+    /// 10x Chromium v3 (16bp barcode, 12bp UMI).
+    ChromiumV3,
+    /// Drop-seq (12bp barcode, 8bp UMI).
+    DropSeq,
+}
+
+impl fmt::Display for KnownChemistry {
+    /// Writes the name by which this chemistry is commonly known to
+    /// `alevin-fry` based tools.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KnownChemistry::ChromiumV2 => write!(f, "10xv2"),
+            KnownChemistry::ChromiumV3 => write!(f, "10xv3"),
+            KnownChemistry::DropSeq => write!(f, "dropseq"),
+        }
+    }
+}
+
+const CHROMIUM_V2_R1: &[GeomPiece] = &[
+    GeomPiece::Barcode(GeomLen::FixedLen(16)),
+    GeomPiece::Umi(GeomLen::FixedLen(10)),
+];
+const CHROMIUM_V2_R1_DISCARD: &[GeomPiece] = &[
+    GeomPiece::Barcode(GeomLen::FixedLen(16)),
+    GeomPiece::Umi(GeomLen::FixedLen(10)),
+    GeomPiece::Discard(GeomLen::Unbounded),
+];
+const CHROMIUM_V3_R1: &[GeomPiece] = &[
+    GeomPiece::Barcode(GeomLen::FixedLen(16)),
+    GeomPiece::Umi(GeomLen::FixedLen(12)),
+];
+const CHROMIUM_V3_R1_DISCARD: &[GeomPiece] = &[
+    GeomPiece::Barcode(GeomLen::FixedLen(16)),
+    GeomPiece::Umi(GeomLen::FixedLen(12)),
+    GeomPiece::Discard(GeomLen::Unbounded),
+];
+const DROPSEQ_R1: &[GeomPiece] = &[
+    GeomPiece::Barcode(GeomLen::FixedLen(12)),
+    GeomPiece::Umi(GeomLen::FixedLen(8)),
+];
+const DROPSEQ_R1_DISCARD: &[GeomPiece] = &[
+    GeomPiece::Barcode(GeomLen::FixedLen(12)),
+    GeomPiece::Umi(GeomLen::FixedLen(8)),
+    GeomPiece::Discard(GeomLen::Unbounded),
+];
+const BIOLOGICAL_R2: &[GeomPiece] = &[GeomPiece::ReadSeq(GeomLen::Unbounded, None)];
+
+/// The geometries (read 1, read 2) of each `KnownChemistry`.  A chemistry may be
+/// listed more than once, e.g. with and without a trailing `x:` on read 1, since
+/// both forms are in common use.
+const KNOWN_CHEMISTRIES: &[(KnownChemistry, &[GeomPiece], &[GeomPiece])] = &[
+    (KnownChemistry::ChromiumV2, CHROMIUM_V2_R1, BIOLOGICAL_R2),
+    (
+        KnownChemistry::ChromiumV2,
+        CHROMIUM_V2_R1_DISCARD,
+        BIOLOGICAL_R2,
+    ),
+    (KnownChemistry::ChromiumV3, CHROMIUM_V3_R1, BIOLOGICAL_R2),
+    (
+        KnownChemistry::ChromiumV3,
+        CHROMIUM_V3_R1_DISCARD,
+        BIOLOGICAL_R2,
+    ),
+    (KnownChemistry::DropSeq, DROPSEQ_R1, BIOLOGICAL_R2),
+    (KnownChemistry::DropSeq, DROPSEQ_R1_DISCARD, BIOLOGICAL_R2),
+];
+
+/// The bounds on the number of bases that a read described by a
+/// sequence of `GeomPiece`s can consume.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct GeomLenBounds {
+    /// The minimum number of bases the read must contain.
+    pub min: u32,
+    /// The maximum number of bases the read may contain, or `None` if
+    /// the read contains an unbounded piece.
+    pub max: Option<u32>,
+}
+
+/// Computes the `GeomLenBounds` of a single read by summing the minimum and
+/// maximum lengths of each of its constituent `GeomPiece`s.  Both sums saturate at
+/// `u32::MAX` rather than overflowing.
+fn len_bounds_single_read(geom_pieces: &[GeomPiece]) -> GeomLenBounds {
+    let mut min = 0_u32;
+    let mut max = Some(0_u32);
+    for gp in geom_pieces {
+        let gl = gp.geom_len();
+        min = min.saturating_add(gl.min_len());
+        max = match (max, gl.max_len()) {
+            (Some(m), Some(h)) => Some(m.saturating_add(h)),
+            _ => None,
+        };
+    }
+    GeomLenBounds { min, max }
+}
+
+/// Computes the minimum length of a single read that could be laid out according to
+/// `geom_pieces`, where every piece has its minimum length and each `Unbounded` piece
+/// holds at least 1 base.  The total saturates at `u32::MAX` rather than overflowing.
+fn min_required_len_single_read(geom_pieces: &[GeomPiece]) -> u32 {
+    geom_pieces
+        .iter()
+        .map(|gp| match gp.geom_len() {
+            GeomLen::Unbounded => 1,
+            gl => gl.min_len(),
+        })
+        .fold(0, u32::saturating_add)
+}
+
+/// Computes a "typical" length of a single read, where a `LenRange(l, h)` piece
+/// contributes its midpoint `l + (h - l) / 2`, an open-ended `LenRangeFrom(l)` piece
+/// contributes `l`, and an `Unbounded` piece contributes `unbounded_len`.  The total
+/// saturates at `u32::MAX` rather than overflowing.
+fn nominal_len_single_read(geom_pieces: &[GeomPiece], unbounded_len: u32) -> u32 {
+    geom_pieces
+        .iter()
+        .map(|gp| match gp.geom_len() {
+            GeomLen::FixedLen(x) | GeomLen::LenRangeFrom(x) => x,
+            GeomLen::LenRange(l, h) => l + h.saturating_sub(l) / 2,
+            GeomLen::Unbounded => unbounded_len,
+        })
+        .fold(0, u32::saturating_add)
+}
+
+/// The number of pieces of each type in (part of) a geometry.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PieceCounts {
+    /// The number of cellular barcode segments.
+    pub barcode: usize,
+    /// The number of sample barcode segments.
+    pub sample_barcode: usize,
+    /// The number of UMI segments.
+    pub umi: usize,
+    /// The number of biological read segments.
+    pub readseq: usize,
+    /// The number of discard segments.
+    pub discard: usize,
+    /// The number of fixed sequence anchors.
+    pub fixed: usize,
+    /// The number of custom tagged segments.
+    pub tag: usize,
+    /// The number of homopolymer segments.
+    pub homopolymer: usize,
+    /// The number of spacer segments.
+    pub spacer: usize,
+}
+
+impl PieceCounts {
+    /// Tallies the pieces of each type in `geom_pieces`.
+    fn from_geom_pieces<'a>(geom_pieces: impl Iterator<Item = &'a GeomPiece>) -> Self {
+        let mut counts = Self::default();
+        for gp in geom_pieces {
+            match gp {
+                GeomPiece::Barcode(_) => counts.barcode += 1,
+                GeomPiece::SampleBarcode(_) => counts.sample_barcode += 1,
+                GeomPiece::Umi(_) => counts.umi += 1,
+                GeomPiece::ReadSeq(_, _) => counts.readseq += 1,
+                GeomPiece::Discard(_) => counts.discard += 1,
+                GeomPiece::Fixed(_, _) => counts.fixed += 1,
+                GeomPiece::Tag(_, _) => counts.tag += 1,
+                GeomPiece::Homopolymer(_, _) => counts.homopolymer += 1,
+                GeomPiece::Spacer(_) => counts.spacer += 1,
+            }
+        }
+        counts
+    }
+}
+
+/// An aggregate description of a geometry, as returned by
+/// [`FragmentGeomDesc::summary`].  Its `Display` implementation renders
+/// a human-readable, multi-line report.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GeomSummary {
+    /// The number of pieces of each type across all reads.
+    pub counts: PieceCounts,
+    /// For each read, the summed length of its fixed-length pieces (including
+    /// fixed sequence anchors).
+    pub fixed_len: Vec<u32>,
+    /// For each read, whether it contains an unbounded or open-ended piece.
+    pub unbounded: Vec<bool>,
+    /// The read number and sequence of each fixed sequence anchor, in order.
+    pub anchors: Vec<(u8, String)>,
+}
+
+impl fmt::Display for GeomSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let c = &self.counts;
+        writeln!(
+            f,
+            "segments: {} barcode, {} sample barcode, {} UMI, {} read, {} discard, {} fixed, {} tag, {} homopolymer, {} spacer",
+            c.barcode, c.sample_barcode, c.umi, c.readseq, c.discard, c.fixed, c.tag, c.homopolymer, c.spacer
+        )?;
+        for (i, (fixed_len, unbounded)) in self.fixed_len.iter().zip(&self.unbounded).enumerate() {
+            writeln!(
+                f,
+                "read {}: {} fixed-length bases, {}",
+                i + 1,
+                fixed_len,
+                if *unbounded { "unbounded" } else { "bounded" }
+            )?;
+        }
+        if self.anchors.is_empty() {
+            write!(f, "anchors: none")
+        } else {
+            let anchors = self
+                .anchors
+                .iter()
+                .map(|(read, seq)| format!("{} (read {})", seq, read))
+                .collect::<Vec<String>>();
+            write!(f, "anchors: {}", anchors.join(", "))
+        }
+    }
+}
+
+/// The first position at which two geometries differ, as reported by
+/// [`FragmentGeomDesc::diff`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GeomDiff {
+    /// The number of the read in which the geometries differ.  Index reads
+    /// I1 and I2 are numbered after the last read of the fragment.
+    pub read: u8,
+    /// The 0-based index of the first differing piece within the read.
+    pub index: usize,
+    /// The piece of the left geometry at this position, or `None` if its read ends here.
+    pub left: Option<GeomPiece>,
+    /// The piece of the right geometry at this position, or `None` if its read ends here.
+    pub right: Option<GeomPiece>,
+}
+
+/// Returns the index of the first position at which `left` and `right` differ,
+/// along with the piece of each (if any) at that position.
+fn first_divergence_single_read(
+    left: &[GeomPiece],
+    right: &[GeomPiece],
+) -> Option<(usize, Option<GeomPiece>, Option<GeomPiece>)> {
+    let n = left.len().max(right.len());
+    (0..n).find_map(|i| {
+        let (l, r) = (left.get(i), right.get(i));
+        if l == r {
+            None
+        } else {
+            Some((i, l.cloned(), r.cloned()))
+        }
+    })
+}
+
+/// The location of a single cellular barcode segment within a read.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct BarcodeSpan {
+    /// The number of the read containing the barcode.
+    pub read: u8,
+    /// The 0-based offset of the first base of the barcode within the read.
+    pub start: u32,
+    /// The length of the barcode.
+    pub len: GeomLen,
+}
+
+/// Pairs each piece of a single read with its 0-based start offset within the read.
+/// Fixed-length pieces and fixed sequence anchors advance the offset by their length,
+/// while the offset of every piece following a ranged or unbounded piece can't be
+/// determined, and is given as `None`.  So is that of any piece starting past `u32::MAX`.
+fn piece_offsets_single_read(geom_pieces: &[GeomPiece]) -> Vec<(Option<u32>, &GeomPiece)> {
+    let mut offsets = Vec::with_capacity(geom_pieces.len());
+    let mut offset = Some(0_u32);
+    for gp in geom_pieces {
+        offsets.push((offset, gp));
+        offset = match (offset, gp.geom_len()) {
+            (Some(o), GeomLen::FixedLen(x)) => o.checked_add(x),
+            _ => None,
+        };
+    }
+    offsets
+}
+
+/// Computes the summed length of the leading run of fixed-length pieces
+/// (`FixedLen` or fixed sequence) of a single read, stopping at the first
+/// piece that is ranged or unbounded.  The sum saturates at `u32::MAX` rather than
+/// overflowing.
+fn fixed_prefix_len_single_read(geom_pieces: &[GeomPiece]) -> u32 {
+    let mut len = 0_u32;
+    for gp in geom_pieces {
+        match gp.geom_len() {
+            GeomLen::FixedLen(x) => len = len.saturating_add(x),
+            _ => break,
+        }
+    }
+    len
+}
+
+/// Replaces the trailing `Unbounded` piece of the read `read_num`, described by
+/// `geom_pieces`, with a `FixedLen` piece of the same type, whose length is `read_len`
+/// less the total length of the preceding pieces.  Fails if the read doesn't end in
+/// an unbounded piece, if any preceding piece isn't of fixed length, or if the
+/// preceding pieces already consume all `read_len` bases.
+fn resolve_unbounded_single_read(
+    geom_pieces: &[GeomPiece],
+    read_num: usize,
+    read_len: u32,
+) -> Result<Vec<GeomPiece>> {
+    let (last, preceding) = match geom_pieces.split_last() {
+        Some((last, preceding)) if !last.is_bounded() => (last, preceding),
+        _ => bail!(
+            "Read {} has no trailing unbounded piece to resolve to a length of {}.",
+            read_num,
+            read_len
+        ),
+    };
+    if let Some(gp) = preceding.iter().find(|gp| !gp.is_fixed_len()) {
+        bail!(
+            "Can't resolve the unbounded piece of read {}, as it is preceded by {}, which has a variable length.",
+            read_num,
+            gp
+        );
+    }
+    let preceding_len = fixed_prefix_len_single_read(preceding);
+    if preceding_len >= read_len {
+        bail!(
+            "Read {} has {} bases preceding its unbounded piece, which leaves no room for it in a read of length {}.",
+            read_num,
+            preceding_len,
+            read_len
+        );
+    }
+    let resolved_len = read_len - preceding_len;
+    if let GeomPiece::Barcode(GeomLen::LenRangeFrom(min))
+    | GeomPiece::SampleBarcode(GeomLen::LenRangeFrom(min))
+    | GeomPiece::Umi(GeomLen::LenRangeFrom(min))
+    | GeomPiece::ReadSeq(GeomLen::LenRangeFrom(min), _)
+    | GeomPiece::Discard(GeomLen::LenRangeFrom(min))
+    | GeomPiece::Tag(_, GeomLen::LenRangeFrom(min))
+    | GeomPiece::Homopolymer(_, GeomLen::LenRangeFrom(min))
+    | GeomPiece::Spacer(GeomLen::LenRangeFrom(min)) = last
+    {
+        if resolved_len < *min {
+            bail!(
+                "Resolving {} in read {} would give it a length of {}, which is below its minimum.",
+                last,
+                read_num,
+                resolved_len
+            );
+        }
+    }
+    let resolved = last.with_len(GeomLen::FixedLen(resolved_len));
+    let mut resolved_pieces = preceding.to_vec();
+    resolved_pieces.push(resolved);
+    Ok(resolved_pieces)
+}
+
+/// Collapses each run of consecutive `Discard` pieces in `geom_pieces` into a single
+/// `Discard` piece whose length is the sum of the lengths in the run.  If any piece in
+/// the run is unbounded, the result is `Discard(Unbounded)`; otherwise, the bounds of
+/// the run are summed, yielding a `FixedLen` if they coincide, a `LenRange` if not, and
+/// a `LenRangeFrom` if any piece in the run is open-ended.  The summed bounds saturate at
+/// `u32::MAX` rather than overflowing.
+fn merge_discards_single_read(geom_pieces: &mut Vec<GeomPiece>) {
+    let mut merged = Vec::<GeomPiece>::with_capacity(geom_pieces.len());
+    for gp in geom_pieces.drain(..) {
+        if let (Some(GeomPiece::Discard(prev)), GeomPiece::Discard(cur)) = (merged.last(), &gp) {
+            let bounds = |gl: &GeomLen| match gl {
+                GeomLen::Unbounded => None,
+                gl => Some((gl.min_len(), gl.max_len())),
+            };
+            let gl = match (bounds(prev), bounds(cur)) {
+                (Some((pl, Some(ph))), Some((cl, Some(ch)))) => {
+                    let (l, h) = (pl.saturating_add(cl), ph.saturating_add(ch));
+                    if l == h {
+                        GeomLen::FixedLen(l)
+                    } else {
+                        GeomLen::LenRange(l, h)
+                    }
+                }
+                (Some((pl, _)), Some((cl, _))) => GeomLen::LenRangeFrom(pl.saturating_add(cl)),
+                _ => GeomLen::Unbounded,
+            };
+            *merged.last_mut().unwrap() = GeomPiece::Discard(gl);
+        } else {
+            merged.push(gp);
+        }
+    }
+    *geom_pieces = merged;
+}
+
+/// Renders a single piece as a cell of the HTML-like table that depicts its read in
+/// [`FragmentGeomDesc::to_dot`], labeled with the type of the piece over its length.
+fn dot_cell(gp: &GeomPiece) -> String {
+    let (color, kind) = match gp {
+        GeomPiece::Barcode(_) => ("lightblue", "barcode".to_string()),
+        GeomPiece::SampleBarcode(_) => ("lightcyan", "sample barcode".to_string()),
+        GeomPiece::Umi(_) => ("palegreen", "UMI".to_string()),
+        GeomPiece::ReadSeq(_, _) => ("wheat", "read".to_string()),
+        GeomPiece::Discard(_) => ("lightgrey", "discard".to_string()),
+        GeomPiece::Fixed(NucStr::Seq(s), _) => ("gold", s.clone()),
+        GeomPiece::Tag(name, _) => ("plum", name.clone()),
+        GeomPiece::Homopolymer(b, _) => ("pink", format!("poly-{}", *b as char)),
+        GeomPiece::Spacer(_) => ("white", "spacer".to_string()),
+    };
+    let len = match gp.geom_len() {
+        GeomLen::FixedLen(x) => x.to_string(),
+        GeomLen::LenRange(l, h) => format!("{}-{}", l, h),
+        GeomLen::LenRangeFrom(l) => format!("{}+", l),
+        GeomLen::Unbounded => "*".to_string(),
+    };
+    format!("<td bgcolor=\"{}\">{}<br/>{}</td>", color, kind, len)
+}
+
+/// Returns the only item of `lens`, or `None` if it yields no items or more than one.
+fn sole_geom_len(mut lens: impl Iterator<Item = GeomLen>) -> Option<GeomLen> {
+    match (lens.next(), lens.next()) {
+        (Some(gl), None) => Some(gl),
+        _ => None,
+    }
+}
+
+/// Rewrites any `LenRange(l, l)` in the given pieces as the equivalent `FixedLen(l)`.
+fn normalize_ranges_single_read(geom_pieces: &mut [GeomPiece]) {
+    for gp in geom_pieces.iter_mut() {
+        match gp {
+            GeomPiece::Barcode(gl)
+            | GeomPiece::SampleBarcode(gl)
+            | GeomPiece::Umi(gl)
+            | GeomPiece::Discard(gl)
+            | GeomPiece::ReadSeq(gl, _)
+            | GeomPiece::Tag(_, gl)
+            | GeomPiece::Homopolymer(_, gl)
+            | GeomPiece::Spacer(gl) => {
+                if let GeomLen::LenRange(l, h) = *gl {
+                    if l == h {
+                        *gl = GeomLen::FixedLen(l);
+                    }
+                }
+            }
+            GeomPiece::Fixed(_, _) => {}
+        }
+    }
+}
+
+/// Checks that an individual `GeomPiece` is well-formed; that is, that any
+/// range has a lower bound no greater than its upper bound, and that any
+/// fixed sequence is non-empty and consists only of IUPAC nucleotide codes, and
+/// that any tag name is non-empty and consists only of ASCII letters, digits, and `_`.
+fn validate_piece(gp: &GeomPiece) -> Result<(), FragmentGeomError> {
+    match gp {
+        GeomPiece::Barcode(GeomLen::LenRange(l, h))
+        | GeomPiece::SampleBarcode(GeomLen::LenRange(l, h))
+        | GeomPiece::Umi(GeomLen::LenRange(l, h))
+        | GeomPiece::Discard(GeomLen::LenRange(l, h))
+        | GeomPiece::ReadSeq(GeomLen::LenRange(l, h), _)
+        | GeomPiece::Tag(_, GeomLen::LenRange(l, h))
+        | GeomPiece::Homopolymer(_, GeomLen::LenRange(l, h))
+        | GeomPiece::Spacer(GeomLen::LenRange(l, h))
+            if l > h =>
+        {
+            Err(FragmentGeomError::InvalidRange {
+                segment: gp.to_string(),
+                low: *l,
+                high: *h,
+            })
+        }
+        GeomPiece::Fixed(NucStr::Seq(s), _) if s.is_empty() || !s.bytes().all(is_iupac_code) => {
+            Err(FragmentGeomError::NonACGTFixedSeq(s.clone()))
+        }
+        GeomPiece::Fixed(NucStr::Seq(s), m) if *m as usize >= s.len() => {
+            Err(FragmentGeomError::TooManyMismatches {
+                segment: gp.to_string(),
+                max_mismatch: *m,
+                len: s.len(),
+            })
+        }
+        GeomPiece::Tag(n, _)
+            if n.is_empty() || !n.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'_') =>
+        {
+            Err(FragmentGeomError::InvalidTagName(n.clone()))
+        }
+        GeomPiece::Homopolymer(b, _) if !matches!(b, b'A' | b'C' | b'G' | b'T') => {
+            Err(FragmentGeomError::InvalidHomopolymerBase(*b as char))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Checks each piece of a single read (having number `read_num`) with
+/// `validate_piece`, rejects zero-length pieces, rejects a second unbounded
+/// piece unless a fixed sequence anchor lies between it and the previous one,
+/// and rejects two adjacent variable-length pieces.  It also rejects a read
+/// whose cumulative length (taking the upper bound of each range, and counting
+/// an unbounded piece as 1 base) exceeds `u32::MAX`, so that computing the
+/// offsets of its pieces can't overflow.
+fn validate_read(geom_pieces: &[GeomPiece], read_num: u8) -> Result<(), FragmentGeomError> {
+    let mut seen_unbounded = false;
+    let mut total_len = 0_u64;
+    for (i, gp) in geom_pieces.iter().enumerate() {
+        validate_piece(gp)?;
+        let gl = gp.geom_len();
+        total_len += match gl.max_len() {
+            Some(x) => x as u64,
+            None => gl.min_len().max(1) as u64,
+        };
+        if total_len > u32::MAX as u64 {
+            return Err(FragmentGeomError::OffsetOverflow {
+                segment: gp.to_string(),
+            });
+        }
+        match gp {
+            GeomPiece::Fixed(_, _) => {
+                seen_unbounded = false;
+            }
+            GeomPiece::Barcode(gl)
+            | GeomPiece::SampleBarcode(gl)
+            | GeomPiece::Umi(gl)
+            | GeomPiece::Discard(gl)
+            | GeomPiece::ReadSeq(gl, _)
+            | GeomPiece::Tag(_, gl)
+            | GeomPiece::Homopolymer(_, gl)
+            | GeomPiece::Spacer(gl) => match gl {
+                GeomLen::FixedLen(0) | GeomLen::LenRange(0, _) | GeomLen::LenRangeFrom(0) => {
+                    return Err(FragmentGeomError::ZeroLength {
+                        segment: gp.to_string(),
+                    });
+                }
+                GeomLen::Unbounded | GeomLen::LenRangeFrom(_) => {
+                    if seen_unbounded {
+                        return Err(FragmentGeomError::MultipleUnbounded { read: read_num });
+                    }
+                    seen_unbounded = true;
+                }
+                _ => {}
+            },
+        }
+        // two adjacent variable-length pieces can't be told apart
+        if i > 0 && geom_pieces[i - 1].geom_len().is_variable() && gp.geom_len().is_variable() {
+            return Err(FragmentGeomError::AmbiguousLayout {
+                read: read_num,
+                first: i - 1,
+                second: i,
+            });
+        }
+    }
+    Ok(())
+}
+
+impl FragmentGeomDesc {
+    /// Parses the FGDL description string `arg` into `self`, replacing its
+    /// current contents.  The `GeomPiece` vectors of `self` are cleared rather
+    /// than reallocated, so that, when parsing many descriptions in a row, the
+    /// same `FragmentGeomDesc` can be reused and its buffers will be allocated
+    /// only once.  This is otherwise identical to `FragmentGeomDesc::try_from`;
+    /// if parsing fails, the contents of `self` are unspecified.
+    pub fn parse_into(&mut self, arg: &str) -> Result<(), FragmentGeomError> {
+        self.parse_into_with_opts(arg, ParseOpts::default())
+    }
+
+    /// As [`FragmentGeomDesc::parse_into`], but parsing according to `opts`.
+    pub fn parse_into_with_opts(
+        &mut self,
+        arg: &str,
+        opts: ParseOpts,
+    ) -> Result<(), FragmentGeomError> {
+        self.parse_into_impl(arg, opts, None)
+    }
+
+    fn parse_into_impl(
+        &mut self,
+        arg: &str,
+        opts: ParseOpts,
+        mut spans: Option<&mut Vec<PieceSpan>>,
+    ) -> Result<(), FragmentGeomError> {
+        self.read_labels.clear();
+        self.read_lens.clear();
+        self.read_orientations.clear();
+        self.name = None;
+        self.index1_desc.clear();
+        self.index2_desc.clear();
+
+        check_brace_balance(arg)?;
+
+        match FragGeomParser::parse(Rule::frag_desc, arg) {
+            Ok(fragment_desc) => {
+                // the number of read blocks parsed so far; the read vectors
+                // beyond this point are stale and are dropped at the end.
+                let mut num_reads = 0_usize;
+
+                // read blocks are numbered with a `u8`, and the index reads are numbered
+                // after the reads, so the highest block number must fit in one.
+                let has_rule = |r: Rule| fragment_desc.clone().any(|p| p.as_rule() == r);
+                let highest = fragment_desc
+                    .clone()
+                    .filter(|p| p.as_rule() == Rule::numbered_read_desc)
+                    .count()
+                    + match (has_rule(Rule::index_1_desc), has_rule(Rule::index_2_desc)) {
+                        (_, true) => 2,
+                        (true, false) => 1,
+                        (false, false) => 0,
+                    };
+                if highest > u8::MAX as usize {
+                    return Err(FragmentGeomError::TooManyReads {
+                        found: highest,
+                        max: u8::MAX as usize,
+                    });
+                }
 
                 // Because ident_list is silent, the iterator will contain idents
                 for read_desc in fragment_desc {
                     match read_desc.as_rule() {
-                        Rule::read_1_desc => {
-                            let rd = read_desc.into_inner();
-                            r1_desc = Some(parse_read_description(rd));
+                        Rule::frag_name => {
+                            self.name = Some(read_desc.as_str().to_owned());
                         }
-                        Rule::read_2_desc => {
-                            let rd = read_desc.into_inner();
-                            r2_desc = Some(parse_read_description(rd));
+                        Rule::numbered_read_desc => {
+                            let pos = read_desc.as_span().start();
+                            let mut rd = read_desc.into_inner();
+                            let num_str = rd.next().unwrap().as_str();
+                            let read_num = num_str.parse::<usize>().ok();
+                            // read blocks must be numbered consecutively, starting from 1.
+                            match read_num {
+                                Some(n) if n == num_reads + 1 => {}
+                                Some(n) if n > num_reads + 1 => {
+                                    return Err(FragmentGeomError::MissingRead(
+                                        (num_reads + 1) as u8,
+                                    ));
+                                }
+                                _ => {
+                                    return Err(FragmentGeomError::ParseFailure {
+                                        pos,
+                                        msg: format!(
+                                            "Expected the description of read {}, but found a read numbered {}",
+                                            num_reads + 1,
+                                            num_str
+                                        ),
+                                    });
+                                }
+                            }
+                            if num_reads < self.reads.len() {
+                                self.reads[num_reads].clear();
+                            } else {
+                                self.reads.push(Vec::new());
+                            }
+                            let label = rd
+                                .clone()
+                                .find(|p| p.as_rule() == Rule::read_label)
+                                .map(|p| p.into_inner().as_str().to_owned());
+                            let orientation =
+                                if rd.clone().any(|p| p.as_rule() == Rule::reverse_orientation) {
+                                    Orientation::Reverse
+                                } else {
+                                    Orientation::Forward
+                                };
+                            let read_len =
+                                match rd.clone().find(|p| p.as_rule() == Rule::read_len_hint) {
+                                    Some(p) => {
+                                        let pos = p.as_span().start();
+                                        let len_str = p.into_inner().as_str();
+                                        match len_str.parse::<u32>() {
+                                            Ok(read_len) => Some(read_len),
+                                            Err(_) => {
+                                                return Err(FragmentGeomError::ParseFailure {
+                                                    pos,
+                                                    msg: format!(
+                                                        "The length {} of read {} is too large",
+                                                        len_str,
+                                                        num_reads + 1
+                                                    ),
+                                                });
+                                            }
+                                        }
+                                    }
+                                    None => None,
+                                };
+                            parse_read_description(
+                                rd.filter(|p| p.as_rule() == Rule::read_desc),
+                                &mut self.reads[num_reads],
+                                (num_reads + 1) as u8,
+                                spans.as_deref_mut(),
+                            )?;
+                            self.read_labels.push(label);
+                            self.read_lens.push(read_len);
+                            self.read_orientations.push(orientation);
+                            num_reads += 1;
+                        }
+                        Rule::index_1_desc => {
+                            parse_read_description(
+                                read_desc.into_inner(),
+                                &mut self.index1_desc,
+                                (num_reads + 1) as u8,
+                                spans.as_deref_mut(),
+                            )?;
+                        }
+                        Rule::index_2_desc => {
+                            parse_read_description(
+                                read_desc.into_inner(),
+                                &mut self.index2_desc,
+                                (num_reads + 2) as u8,
+                                spans.as_deref_mut(),
+                            )?;
                         }
                         Rule::EOI => {}
                         e => {
-                            dbg!("{:?}", e);
-                            bail!("Expected to parse a description for read 1, or 2, but didn't find the corresponding rule!")
+                            return Err(FragmentGeomError::ParseFailure {
+                                pos: read_desc.as_span().start(),
+                                msg: format!("Expected to parse a read or index read description, but found {:?}", e),
+                            });
                         }
                     };
                 }
 
-                if let (Some(read1_desc), Some(read2_desc)) = (r1_desc, r2_desc) {
-                    Ok(FragmentGeomDesc {
-                        read1_desc,
-                        read2_desc,
-                    })
-                } else {
-                    bail!("Was not able to obtain a succesful parse for both read 1 and read 2.")
+                self.reads.truncate(num_reads);
+                while let Some(None) = self.read_labels.last() {
+                    self.read_labels.pop();
+                }
+                while let Some(None) = self.read_lens.last() {
+                    self.read_lens.pop();
                 }
+                while let Some(Orientation::Forward) = self.read_orientations.last() {
+                    self.read_orientations.pop();
+                }
+                let all_pieces = self
+                    .reads
+                    .iter()
+                    .chain([&self.index1_desc, &self.index2_desc])
+                    .flatten();
+                for gp in all_pieces {
+                    if let GeomPiece::Fixed(NucStr::Seq(s), _) = gp {
+                        if let Some(base) = s.chars().find(|b| !opts.alphabet.contains(*b)) {
+                            return Err(FragmentGeomError::InvalidBase {
+                                base,
+                                alphabet: opts.alphabet,
+                            });
+                        }
+                    }
+                    match (gp, opts.max_fixed_seq_len, opts.max_segment_len) {
+                        (GeomPiece::Fixed(NucStr::Seq(s), _), Some(max), _) if s.len() > max => {
+                            return Err(FragmentGeomError::FixedSeqTooLong { len: s.len(), max });
+                        }
+                        (GeomPiece::Fixed(_, _), _, _) => {}
+                        (gp, _, Some(max)) => {
+                            let gl = gp.geom_len();
+                            if gl.max_len().unwrap_or(gl.min_len()) > max {
+                                return Err(FragmentGeomError::SegmentTooLong {
+                                    segment: gp.to_string(),
+                                    max,
+                                });
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                if opts.u_to_t {
+                    let all_pieces = self
+                        .reads
+                        .iter_mut()
+                        .chain([&mut self.index1_desc, &mut self.index2_desc])
+                        .flatten();
+                    for gp in all_pieces {
+                        if let GeomPiece::Fixed(NucStr::Seq(s), _) = gp {
+                            *s = s.replace('U', "T");
+                        }
+                    }
+                }
+                // the grammar doesn't enforce that lengths and ranges
+                // are sensible, so check that here.
+                self.validate()
+            }
+            Err(e) => {
+                let pos = parse_error_pos(&e);
+                // if parsing only failed at the end of a complete description, then the
+                // description is fine, but is followed by something else.
+                let prefix_end = FragGeomParser::parse(Rule::frag_prefix_desc, arg)
+                    .ok()
+                    .and_then(|mut pairs| pairs.next())
+                    .map(|p| p.as_span().end());
+                if let Some(at) = prefix_end.filter(|at| *at < arg.len() && pos <= *at) {
+                    // report any problem with the description itself first.
+                    self.parse_into_impl(&arg[..at], opts, None)?;
+                    return Err(FragmentGeomError::TrailingInput {
+                        at,
+                        text: arg[at..].to_owned(),
+                    });
+                }
+                Err(FragmentGeomError::ParseFailure {
+                    pos,
+                    msg: format_parse_error(arg, &e),
+                })
             }
-            Err(e) => Err(anyhow!(
-                "Could not succesfully parse geometry description {}.\nParse Error : {:#?}",
-                arg,
-                e
-            )),
         }
     }
+
+    /// Parses the FGDL description string `arg` according to `opts`.  With the default
+    /// options, this is identical to `FragmentGeomDesc::try_from`.
+    pub fn try_from_with_opts(arg: &str, opts: ParseOpts) -> Result<Self, FragmentGeomError> {
+        let mut frag_desc = FragmentGeomDesc {
+            reads: Vec::new(),
+            read_labels: Vec::new(),
+            read_lens: Vec::new(),
+            read_orientations: Vec::new(),
+            name: None,
+            index1_desc: Vec::new(),
+            index2_desc: Vec::new(),
+        };
+        frag_desc.parse_into_with_opts(arg, opts)?;
+        Ok(frag_desc)
+    }
+
+    /// Parses the FGDL description string `arg` as `FragmentGeomDesc::try_from` does,
+    /// but also returns the location of each parsed piece within `arg`, as a
+    /// `(read_number, piece_index, byte_range)` triple, in the order in which the
+    /// pieces appear.  Index reads I1 and I2 are numbered after the last read of the
+    /// fragment.  This is useful for, e.g., highlighting individual segments in an
+    /// editor.
+    pub fn try_from_with_spans(
+        arg: &str,
+    ) -> Result<(FragmentGeomDesc, Vec<PieceSpan>), FragmentGeomError> {
+        let mut frag_desc = FragmentGeomDesc {
+            reads: Vec::new(),
+            read_labels: Vec::new(),
+            read_lens: Vec::new(),
+            read_orientations: Vec::new(),
+            name: None,
+            index1_desc: Vec::new(),
+            index2_desc: Vec::new(),
+        };
+        let mut spans = Vec::new();
+        frag_desc.parse_into_impl(arg, ParseOpts::default(), Some(&mut spans))?;
+        Ok((frag_desc, spans))
+    }
+
+    /// Builds a `FragmentGeomDesc` from the separate fields of a chemistry record: an
+    /// optional geometry `name`, and the piece strings of read 1 (`r1`) and read 2
+    /// (`r2`), e.g. `b[16]u[12]x:` and `r:`.  Each piece string is parsed on its own,
+    /// without the surrounding `1{...}` braces, and an empty `r2` denotes a
+    /// single-end fragment.  The position of a `ParseFailure` is relative to the
+    /// field in which it occurred.  The result is checked as parsing would check it,
+    /// so e.g. `FragmentGeomDesc::from_fields(Some("10xv3"), "b[16]u[12]x:", "r:")`
+    /// is the same as parsing `10xv3:1{b[16]u[12]x:}2{r:}`.
+    pub fn from_fields(name: Option<&str>, r1: &str, r2: &str) -> Result<Self, FragmentGeomError> {
+        if let Some(name) = name {
+            FragGeomParser::parse(Rule::name_desc, name).map_err(|e| {
+                FragmentGeomError::ParseFailure {
+                    pos: parse_error_pos(&e),
+                    msg: format_parse_error(name, &e),
+                }
+            })?;
+        }
+        let mut reads = vec![parse_read_field(r1, 1)?];
+        if !r2.is_empty() {
+            reads.push(parse_read_field(r2, 2)?);
+        }
+        let frag_desc = Self {
+            reads,
+            read_labels: Vec::new(),
+            read_lens: Vec::new(),
+            read_orientations: Vec::new(),
+            name: name.map(|n| n.to_owned()),
+            index1_desc: Vec::new(),
+            index2_desc: Vec::new(),
+        };
+        frag_desc.validate()?;
+        Ok(frag_desc)
+    }
+
+    /// Combines two single-read geometries into a paired-end one, whose read 1 is read 1
+    /// of `r1` and whose read 2 is read 1 of `r2`.  The label, declared length, and
+    /// orientation of each input's read 1 are carried over to the corresponding read, while the name and index reads of the
+    /// result are taken from `r1` alone.  If either input has more than one read block
+    /// (counting empty ones, unlike [`FragmentGeomDesc::num_reads`]), `TooManyReads` is
+    /// returned, and the result is checked with
+    /// [`FragmentGeomDesc::validate`].
+    pub fn from_reads(
+        r1: FragmentGeomDesc,
+        r2: FragmentGeomDesc,
+    ) -> Result<Self, FragmentGeomError> {
+        for input in [&r1, &r2] {
+            // count every read block, as `read_pair` does, so that a read left empty
+            // (e.g. by `without_discards`) can't hide a second read.
+            if input.reads.len() > 1 {
+                return Err(FragmentGeomError::TooManyReads {
+                    found: input.reads.len(),
+                    max: 1,
+                });
+            }
+        }
+        let r2_label = r2.read_label(1).map(|l| l.to_owned());
+        let r2_len = r2.read_len_hint(1);
+        let r2_orientation = r2.read_orientation(1);
+        let mut paired = r1;
+        paired.extend_read(1, r2.reads.into_iter().flatten());
+        paired.set_read_label(2, r2_label);
+        paired.set_read_len_hint(2, r2_len);
+        paired.set_read_orientation(2, r2_orientation);
+        paired.validate()?;
+        Ok(paired)
+    }
+
+    /// Builds a `FragmentGeomDesc` directly from the `GeomPiece`s of read 1 (`read1`)
+    /// and read 2 (`read2`), without going through a string representation.  The
+    /// resulting geometry is checked with [`FragmentGeomDesc::validate`], and the
+    /// corresponding `FragmentGeomError` is returned if it is invalid.  As with parsing,
+    /// an empty `read2` denotes a single-end fragment.  Fragments with more than two
+    /// reads can be constructed with [`FragmentGeomDescBuilder::push_read`].
+    pub fn new(read1: Vec<GeomPiece>, read2: Vec<GeomPiece>) -> Result<Self, FragmentGeomError> {
+        let reads = if read2.is_empty() {
+            vec![read1]
+        } else {
+            vec![read1, read2]
+        };
+        let frag_desc = Self {
+            reads,
+            read_labels: Vec::new(),
+            read_lens: Vec::new(),
+            read_orientations: Vec::new(),
+            name: None,
+            index1_desc: Vec::new(),
+            index2_desc: Vec::new(),
+        };
+        frag_desc.validate()?;
+        Ok(frag_desc)
+    }
+
+    /// Checks that this geometry is sensible, returning the first problem found as a
+    /// `FragmentGeomError`.  A geometry is rejected if
+    ///  * there is no read 1, or any read has no pieces (`MissingRead`),
+    ///  * any piece has a fixed length of 0, or a range with a lower bound of 0 (`ZeroLength`),
+    ///  * any range has a lower bound greater than its upper bound (`InvalidRange`),
+    ///  * any fixed sequence or tag name is malformed (`NonACGTFixedSeq`, `InvalidTagName`),
+    ///  * any read contains two unbounded (or open-ended, e.g. `b[8-]`) pieces that are not
+    ///    separated by a fixed sequence anchor, since there is then no way to tell where
+    ///    one ends and the next begins (`MultipleUnbounded`), or
+    ///  * any read contains two adjacent variable-length pieces, with no fixed-length
+    ///    piece or anchor between them to tell where one ends (`AmbiguousLayout`).
+    ///
+    /// This is called on every geometry produced by parsing.
+    pub fn validate(&self) -> Result<(), FragmentGeomError> {
+        if self.reads.is_empty() {
+            return Err(FragmentGeomError::MissingRead(1));
+        }
+        for (i, geom_pieces) in self.reads.iter().enumerate() {
+            let read_num = (i + 1) as u8;
+            if geom_pieces.is_empty() {
+                return Err(FragmentGeomError::MissingRead(read_num));
+            }
+            validate_read(geom_pieces, read_num)?;
+            if let Some(Some(got)) = self.read_lens.get(i) {
+                let needed = min_required_len_single_read(geom_pieces);
+                if *got < needed {
+                    return Err(FragmentGeomError::ReadTooShort {
+                        read: read_num,
+                        needed,
+                        got: *got,
+                    });
+                }
+            }
+        }
+        // index reads are numbered after the last read, and those numbers must fit in a `u8`
+        let highest = self.reads.len()
+            + match (self.index1_desc.is_empty(), self.index2_desc.is_empty()) {
+                (_, false) => 2,
+                (false, true) => 1,
+                (true, true) => 0,
+            };
+        if highest > u8::MAX as usize {
+            return Err(FragmentGeomError::TooManyReads {
+                found: highest,
+                max: u8::MAX as usize,
+            });
+        }
+        let (index1_num, index2_num) = self.index_read_nums();
+        validate_read(&self.index1_desc, index1_num)?;
+        validate_read(&self.index2_desc, index2_num)
+    }
+
+    /// Returns the numbers of index read 1 and index read 2, which follow the last read.
+    /// The number of an index read that is absent saturates at `u8::MAX`, while that of
+    /// one that is present always fits once [`FragmentGeomDesc::validate`] has passed.
+    fn index_read_nums(&self) -> (u8, u8) {
+        let num = |k: usize| u8::try_from(self.reads.len() + k).unwrap_or(u8::MAX);
+        (num(1), num(2))
+    }
+
+    /// Checks that the UMI of this fragment, if any, occupies a single region; that is,
+    /// that all of its UMI segments are adjacent to one another within a single read
+    /// (as in a UMI split into consecutive parts, e.g. `u[6]u[6]`, which concatenate).
+    /// Otherwise, `MultipleUmiRegions` is returned, giving the read of each separate
+    /// region.  This catches e.g. `1{b[16]u[12]}2{u[8]r:}`, where the UMI has been
+    /// duplicated onto read 2, and `1{u[6]b[16]u[6]}2{r:}`.  Index reads are not checked.
+    pub fn validate_umi(&self) -> Result<(), FragmentGeomError> {
+        let mut regions = Vec::new();
+        for (i, read_desc) in self.reads.iter().enumerate() {
+            let mut in_umi = false;
+            for gp in read_desc {
+                let is_umi = matches!(gp, GeomPiece::Umi(_));
+                if is_umi && !in_umi {
+                    regions.push((i + 1) as u8);
+                }
+                in_umi = is_umi;
+            }
+        }
+        if regions.len() > 1 {
+            return Err(FragmentGeomError::MultipleUmiRegions { reads: regions });
+        }
+        Ok(())
+    }
+
+    /// Returns the minimum number of bases that read `read` (numbered from 1) must have
+    /// for its technical (i.e. non-`ReadSeq`) content to fit; that is, the sum of the
+    /// minimum lengths of its pieces (the length of each fixed piece or fixed sequence,
+    /// and the lower bound of each range) up to and including its last technical piece.
+    /// Unlike [`FragmentGeomDesc::total_len_range`], any trailing read sequence doesn't
+    /// count, so this is 28 for read 1 of `1{b[16]u[12]x:}2{r:}`, and 0 for its read 2
+    /// (or for a read that is absent).  A length that would exceed `u32::MAX` saturates
+    /// at `u32::MAX`.
+    pub fn min_required_len(&self, read: u8) -> u32 {
+        let Some(read_desc) = (read as usize)
+            .checked_sub(1)
+            .and_then(|i| self.reads.get(i))
+        else {
+            return 0;
+        };
+        let technical_end = read_desc
+            .iter()
+            .rposition(|gp| !matches!(gp, GeomPiece::ReadSeq(_, _)))
+            .map_or(0, |i| i + 1);
+        read_desc[..technical_end]
+            .iter()
+            .map(|gp| gp.geom_len().min_len())
+            .fold(0, u32::saturating_add)
+    }
+
+    /// Returns the `GeomLenBounds` for read 1 and read 2 (in that order), giving the
+    /// minimum and maximum number of bases that a read could consume under this geometry.
+    /// A `FixedLen(x)` piece contributes `x` to both bounds, a `LenRange(l, h)` contributes
+    /// `l` to the minimum and `h` to the maximum, and a fixed sequence contributes its
+    /// length to both.  Any `Unbounded` piece makes the maximum for its read `None`, and
+    /// a bound that would exceed `u32::MAX` saturates at `u32::MAX`.
+    pub fn total_len_range(&self) -> (GeomLenBounds, GeomLenBounds) {
+        (
+            len_bounds_single_read(self.read1_desc()),
+            len_bounds_single_read(self.read2_desc()),
+        )
+    }
+
+    /// Checks that reads 1 and 2 of lengths `r1_len` and `r2_len` can hold the pieces
+    /// described for them; that is, that each is at least as long as the sum of the
+    /// minimum lengths of its pieces (counting the lower bound of each range, the
+    /// length of each fixed sequence, and 1 base for each `Unbounded` piece).  Returns
+    /// `FragmentGeomError::ReadTooShort` for the first read that is too short.  A read
+    /// that is absent from this geometry (e.g. read 2 of a single-end geometry) is not
+    /// checked, and nor are any reads after read 2.
+    pub fn is_satisfiable(&self, r1_len: u32, r2_len: u32) -> Result<(), FragmentGeomError> {
+        for (i, (read_desc, got)) in self.reads.iter().zip([r1_len, r2_len]).enumerate() {
+            let needed = min_required_len_single_read(read_desc);
+            if got < needed {
+                return Err(FragmentGeomError::ReadTooShort {
+                    read: (i + 1) as u8,
+                    needed,
+                    got,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a representative (rather than worst-case) length of read 1 and read 2 (in
+    /// that order), suitable for e.g. sizing buffers.  Fixed-length pieces contribute
+    /// their length, a `LenRange(l, h)` contributes its midpoint `(l + h) / 2` (rounded
+    /// down), and an open-ended `LenRangeFrom(l)` contributes `l`.  `Unbounded` pieces
+    /// contribute 0; use [`FragmentGeomDesc::nominal_len_with`] to choose another value.
+    /// A length that would exceed `u32::MAX` saturates at `u32::MAX`.
+    pub fn nominal_len(&self) -> (u32, u32) {
+        self.nominal_len_with(0)
+    }
+
+    /// As [`FragmentGeomDesc::nominal_len`], but with each `Unbounded` piece contributing
+    /// `unbounded_len` bases.
+    pub fn nominal_len_with(&self, unbounded_len: u32) -> (u32, u32) {
+        (
+            nominal_len_single_read(self.read1_desc(), unbounded_len),
+            nominal_len_single_read(self.read2_desc(), unbounded_len),
+        )
+    }
+
+    /// Returns true if every piece in the reads of this fragment has a deterministic
+    /// length; that is, if every piece is either `FixedLen` or a fixed sequence, and
+    /// there are no `LenRange` or `Unbounded` pieces.  Index reads are not considered.
+    pub fn is_fully_fixed(&self) -> bool {
+        self.iter_pieces().all(|(_, gp)| gp.is_fixed_len())
+    }
+
+    /// Returns the exact lengths of read 1 and read 2 (in that order) if this geometry
+    /// is fully fixed-length (see [`FragmentGeomDesc::is_fully_fixed`]), and `None`
+    /// otherwise.  The length of read 2 is 0 for a single-end fragment.
+    pub fn total_fixed_len(&self) -> Option<(u32, u32)> {
+        if !self.is_fully_fixed() {
+            return None;
+        }
+        let (r1, r2) = self.total_len_range();
+        Some((r1.min, r2.min))
+    }
+
+    /// Returns the number of leading bases of read 1 and read 2 (in that order) that
+    /// lie at a deterministic offset; that is, the summed length of the leading run of
+    /// `FixedLen` and fixed sequence pieces of each read, stopping at the first ranged
+    /// or unbounded piece.  For example, this is 28 for read 1 of `1{b[16]u[12]x:}2{r:}`,
+    /// and 0 for read 1 of `1{b[9-10]f[ACCGT]u[12]b[10]}2{r:}`.  A length that would
+    /// exceed `u32::MAX` saturates at `u32::MAX`.
+    pub fn fixed_prefix_len(&self) -> (u32, u32) {
+        (
+            fixed_prefix_len_single_read(self.read1_desc()),
+            fixed_prefix_len_single_read(self.read2_desc()),
+        )
+    }
+
+    /// Returns a new `FragmentGeomDesc` in which the trailing `Unbounded` piece of read 1
+    /// (respectively read 2) is replaced by a `FixedLen` piece of the same type, when
+    /// `read1_len` (respectively `read2_len`) is provided.  The length of the resolved
+    /// piece is the provided read length less the summed length of the pieces preceding
+    /// it, so that, e.g., `1{b[16]u[12]x:}2{r:}` with read lengths of 150 and 91 becomes
+    /// `1{b[16]u[12]x[122]}2{r[91]}`.  An error is returned if a read for which a length
+    /// is given has no trailing unbounded piece, has a piece of variable length before
+    /// it, or if its preceding pieces already consume the whole read.  A trailing
+    /// open-ended piece (e.g. `r[50-]`) is resolved in the same way, but it is an error
+    /// if the resolved length would fall below its minimum.
+    ///
+    /// When no length is given for a read, its declared length (see
+    /// [`FragmentGeomDesc::read_len_hint`]) is used instead, if the read has a trailing
+    /// unbounded or open-ended piece to resolve; so `1{b[16]u[12]x:}2@91{r:}` resolves
+    /// read 2 to `2@91{r[91]}` even if `read2_len` is `None`.
+    pub fn resolve_unbounded(
+        &self,
+        read1_len: Option<u32>,
+        read2_len: Option<u32>,
+    ) -> Result<FragmentGeomDesc> {
+        let mut resolved = self.clone();
+        for (i, read_len) in [read1_len, read2_len].into_iter().enumerate() {
+            let read_len = read_len.or_else(|| {
+                let read_desc = self.reads.get(i)?;
+                match read_desc.last() {
+                    Some(last) if !last.is_bounded() => self.read_len_hint((i + 1) as u8),
+                    _ => None,
+                }
+            });
+            if let Some(read_len) = read_len {
+                let Some(read_desc) = resolved.reads.get_mut(i) else {
+                    bail!(
+                        "A length of {} was given for read {}, but the fragment has no read {}.",
+                        read_len,
+                        i + 1,
+                        i + 1
+                    );
+                };
+                *read_desc = resolve_unbounded_single_read(read_desc, i + 1, read_len)?;
+            }
+        }
+        Ok(resolved)
+    }
+
+    /// Returns a new `FragmentGeomDesc` in which the description of read `read` (numbered
+    /// from 1) has been reverse complemented; that is, the order of its pieces is reversed
+    /// and any fixed sequence anchors are reverse complemented.  The other reads are left
+    /// unchanged.  Applying this transform twice to the same read yields the original
+    /// geometry.
+    ///
+    /// Rather than always returning the transformed geometry, this returns a `Result`:
+    /// a nonexistent `read` results in `MissingRead`, and an anchor containing a `U`
+    /// results in `InvalidBase`.  Since anchors are complemented as DNA (see
+    /// [`NucStr::reverse_complement`]), a `U` would come back as a `T`; use
+    /// [`ParseOpts::with_u_to_t`] to parse RNA anchors as DNA first.
+    pub fn reverse_complement_read(&self, read: u8) -> Result<FragmentGeomDesc, FragmentGeomError> {
+        let mut rc = self.clone();
+        let read_desc = rc.read_mut(read)?;
+        let has_u =
+            |gp: &GeomPiece| matches!(gp, GeomPiece::Fixed(NucStr::Seq(s), _) if s.contains('U'));
+        if read_desc.iter().any(has_u) {
+            return Err(FragmentGeomError::InvalidBase {
+                base: 'U',
+                alphabet: Alphabet::Dna,
+            });
+        }
+        *read_desc = read_desc
+            .iter()
+            .rev()
+            .map(GeomPiece::reverse_complement)
+            .collect();
+        Ok(rc)
+    }
+
+    /// Rewrites this geometry into its canonical (minimal) form by collapsing each run
+    /// of consecutive `Discard` pieces within a read into a single `Discard`.  Fixed
+    /// discards are summed (e.g. `x[4]x[4]` becomes `x[8]`), ranged discards have their
+    /// bounds summed, and a run containing an unbounded discard becomes just `x:`.  The
+    /// `Display` of a canonicalized geometry is therefore the same for any two geometries
+    /// that differ only in how their discarded sequence is split up.
+    pub fn canonicalize(&mut self) {
+        for read_desc in self.reads.iter_mut() {
+            merge_discards_single_read(read_desc);
+        }
+        merge_discards_single_read(&mut self.index1_desc);
+        merge_discards_single_read(&mut self.index2_desc);
+    }
+
+    /// Returns a new `FragmentGeomDesc` that is a copy of this one, but with every
+    /// `Discard` piece removed from each read (and index read), so that e.g.
+    /// `1{b[16]x[4]u[12]x:}2{r:}` becomes `1{b[16]u[12]}2{r:}`.  This gives a compact,
+    /// logical view of the remaining pieces, but it changes their offsets, so it must not
+    /// be used where the positions of pieces within a read matter (e.g. before exporting
+    /// to a tool).  A read consisting only of discards becomes empty, and the result is
+    /// not validated.
+    pub fn without_discards(&self) -> FragmentGeomDesc {
+        let mut stripped = self.clone();
+        let is_kept = |gp: &GeomPiece| !matches!(gp, GeomPiece::Discard(_));
+        for read_desc in stripped.reads.iter_mut() {
+            read_desc.retain(is_kept);
+        }
+        stripped.index1_desc.retain(is_kept);
+        stripped.index2_desc.retain(is_kept);
+        stripped
+    }
+
+    /// Returns a new `FragmentGeomDesc` that is a copy of this one, but with `piece`
+    /// inserted into read `read` (numbered from 1) at the 0-based position `index`,
+    /// shifting the pieces at and after `index` to the right; an `index` equal to the
+    /// number of pieces in the read appends `piece`.  The result is checked with
+    /// [`FragmentGeomDesc::validate`], so e.g. inserting `r:` next to an existing
+    /// unbounded piece fails with `MultipleUnbounded`.  A nonexistent `read` results in
+    /// `MissingRead`, and an `index` past the end of the read in `PieceIndexOutOfRange`.
+    pub fn insert_piece(
+        &self,
+        read: u8,
+        index: usize,
+        piece: GeomPiece,
+    ) -> Result<FragmentGeomDesc, FragmentGeomError> {
+        let mut edited = self.clone();
+        let read_desc = edited.read_mut(read)?;
+        if index > read_desc.len() {
+            return Err(FragmentGeomError::PieceIndexOutOfRange {
+                read,
+                index,
+                len: read_desc.len(),
+            });
+        }
+        read_desc.insert(index, piece);
+        edited.validate()?;
+        Ok(edited)
+    }
+
+    /// Returns a new `FragmentGeomDesc` that is a copy of this one, but with the piece
+    /// at the 0-based position `index` of read `read` (numbered from 1) removed.  As
+    /// with [`FragmentGeomDesc::insert_piece`], the result is validated, so e.g.
+    /// removing the only piece of a read fails with `MissingRead`, and removing the
+    /// anchor between two unbounded pieces fails with `MultipleUnbounded`.
+    pub fn remove_piece(
+        &self,
+        read: u8,
+        index: usize,
+    ) -> Result<FragmentGeomDesc, FragmentGeomError> {
+        let mut edited = self.clone();
+        let read_desc = edited.read_mut(read)?;
+        if index >= read_desc.len() {
+            return Err(FragmentGeomError::PieceIndexOutOfRange {
+                read,
+                index,
+                len: read_desc.len(),
+            });
+        }
+        read_desc.remove(index);
+        edited.validate()?;
+        Ok(edited)
+    }
+
+    /// Returns a mutable reference to the pieces of read `read` (numbered from 1), or
+    /// `MissingRead` if this fragment has no such read.
+    fn read_mut(&mut self, read: u8) -> Result<&mut Vec<GeomPiece>, FragmentGeomError> {
+        (read as usize)
+            .checked_sub(1)
+            .and_then(|i| self.reads.get_mut(i))
+            .ok_or(FragmentGeomError::MissingRead(read))
+    }
+
+    /// Returns a new `FragmentGeomDesc` in which every piece has been replaced by the
+    /// result of applying `f` to it and the number of its read, keeping each piece in
+    /// the same read and position; e.g. `fg.map_pieces(|_, gp| match gp {
+    /// GeomPiece::Umi(_) => gp.as_discard(), gp => gp.clone() })` masks the UMIs of
+    /// `fg`.  As elsewhere, index reads I1 and I2 are numbered after the last read.
+    /// The read labels, declared read lengths, and name are kept, and the result is
+    /// not validated.
+    pub fn map_pieces<F: Fn(u8, &GeomPiece) -> GeomPiece>(&self, f: F) -> FragmentGeomDesc {
+        let map_read = |read_desc: &[GeomPiece], read: u8| {
+            read_desc
+                .iter()
+                .map(|gp| f(read, gp))
+                .collect::<Vec<GeomPiece>>()
+        };
+        let (index1_num, index2_num) = self.index_read_nums();
+        let mut mapped = self.clone();
+        for (i, read_desc) in mapped.reads.iter_mut().enumerate() {
+            *read_desc = map_read(read_desc, (i + 1) as u8);
+        }
+        mapped.index1_desc = map_read(&self.index1_desc, index1_num);
+        mapped.index2_desc = map_read(&self.index2_desc, index2_num);
+        mapped
+    }
+
+    /// Returns `true` if this geometry and `other` describe the same layout, even if
+    /// they are not structurally equal (as compared by `==`).  Both sides are compared
+    /// after applying the following normalizations to every read (and index read):
+    ///
+    /// * any range whose bounds are equal (e.g. `b[16-16]`) is treated as the fixed
+    ///   length it describes (`b[16]`);
+    /// * each run of consecutive discards is merged as by
+    ///   [`FragmentGeomDesc::canonicalize`] (e.g. `x[4]x[4]` is treated as `x[8]`).
+    ///
+    /// As with [`FragmentGeomDesc::diff`], read labels and the geometry name are not
+    /// compared, and nor are declared read lengths.
+    pub fn semantically_eq(&self, other: &FragmentGeomDesc) -> bool {
+        self.semantic_form() == other.semantic_form()
+    }
+
+    /// Returns a short, stable fingerprint of this geometry, suitable as a cache key;
+    /// a 64-bit FNV-1a hash, rendered as 16 lowercase hex digits.  It is computed over
+    /// the same normalized form used by [`FragmentGeomDesc::semantically_eq`], so any two
+    /// semantically equal geometries (e.g. `1{b[16]x[4]x[4]u[12]}2{r:}` and
+    /// `1{b[16]x[8]u[12]}2{r:}`) have the same fingerprint, while their `Display`
+    /// strings differ.  The fingerprint of a geometry is guaranteed to be stable within
+    /// a minor version of this crate, but may change between minor versions.
+    pub fn fingerprint(&self) -> String {
+        const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+        let hash = self
+            .semantic_form()
+            .to_string()
+            .bytes()
+            .fold(FNV_OFFSET_BASIS, |h, b| {
+                (h ^ b as u64).wrapping_mul(FNV_PRIME)
+            });
+        format!("{:016x}", hash)
+    }
+
+    /// Returns the normalized copy of this geometry that is compared by
+    /// [`FragmentGeomDesc::semantically_eq`].
+    fn semantic_form(&self) -> FragmentGeomDesc {
+        let mut fg = self.clone();
+        fg.read_labels.clear();
+        fg.read_lens.clear();
+        fg.name = None;
+        for read_desc in fg.reads.iter_mut() {
+            normalize_ranges_single_read(read_desc);
+        }
+        normalize_ranges_single_read(&mut fg.index1_desc);
+        normalize_ranges_single_read(&mut fg.index2_desc);
+        fg.canonicalize();
+        fg
+    }
+
+    /// Renders this geometry as a [Graphviz](https://graphviz.org) DOT graph, with one
+    /// row of boxes per read (and index read), from top to bottom.  Each box depicts a
+    /// piece, in left-to-right order, and is colored by the type of the piece and labeled
+    /// with its type (or, for an anchor, its sequence) over its length, where `9-10`
+    /// denotes a range, `8+` an open-ended range, and `*` an unbounded length.  The name
+    /// of the geometry, if any, is used as the label of the graph.
+    pub fn to_dot(&self) -> String {
+        let mut rows = self
+            .reads
+            .iter()
+            .enumerate()
+            .map(|(i, read_desc)| (format!("R{}", i + 1), read_desc))
+            .collect::<Vec<_>>();
+        for (label, index_desc) in [("I1", &self.index1_desc), ("I2", &self.index2_desc)] {
+            if !index_desc.is_empty() {
+                rows.push((label.to_string(), index_desc));
+            }
+        }
+
+        let mut dot = String::from("digraph fragment {\n");
+        if let Some(name) = &self.name {
+            dot += &format!("    label=\"{}\";\n", name);
+        }
+        dot += "    node [shape=plaintext];\n";
+        for (label, read_desc) in rows.iter() {
+            let cells = read_desc.iter().map(dot_cell).collect::<String>();
+            dot += &format!(
+                "    {} [label=<<table border=\"0\" cellborder=\"1\" cellspacing=\"0\"><tr><td>{}</td>{}</tr></table>>];\n",
+                label, label, cells
+            );
+        }
+        // invisible edges stack the reads from top to bottom.
+        for pair in rows.windows(2) {
+            dot += &format!("    {} -> {} [style=invis];\n", pair[0].0, pair[1].0);
+        }
+        dot += "}\n";
+        dot
+    }
+
+    /// Returns a [`GeomSummary`] collecting the piece counts, per-read fixed lengths and
+    /// boundedness, and fixed sequence anchors of the reads (but not index reads) of
+    /// this fragment.
+    pub fn summary(&self) -> GeomSummary {
+        let fixed_len = self
+            .reads
+            .iter()
+            .map(|read_desc| {
+                read_desc
+                    .iter()
+                    .filter(|gp| gp.is_fixed_len())
+                    .map(|gp| gp.geom_len().min_len())
+                    .fold(0, u32::saturating_add)
+            })
+            .collect();
+        let unbounded = self
+            .reads
+            .iter()
+            .map(|read_desc| read_desc.iter().any(|gp| !gp.is_bounded()))
+            .collect();
+        let anchors = self
+            .iter_pieces()
+            .filter_map(|(read, gp)| match gp {
+                GeomPiece::Fixed(NucStr::Seq(seq), _) => Some((read, seq.clone())),
+                _ => None,
+            })
+            .collect();
+        GeomSummary {
+            counts: self.counts(),
+            fixed_len,
+            unbounded,
+            anchors,
+        }
+    }
+
+    /// Returns the number of pieces of each type across all reads (but not index reads).
+    pub fn counts(&self) -> PieceCounts {
+        PieceCounts::from_geom_pieces(self.iter_pieces().map(|(_, gp)| gp))
+    }
+
+    /// Returns the number of pieces of each type in read 1 and in read 2 (in that order).
+    pub fn counts_per_read(&self) -> (PieceCounts, PieceCounts) {
+        (
+            PieceCounts::from_geom_pieces(self.read1_desc().iter()),
+            PieceCounts::from_geom_pieces(self.read2_desc().iter()),
+        )
+    }
+
+    /// Returns the full `piscem` `--geometry` string (i.e. `1{...}2{...}`) for this
+    /// geometry.  This is a shorthand for building a [`PiscemGeomDesc`] from the pieces
+    /// of read 1 and read 2 and rendering it.  Since `piscem` can't represent fragments
+    /// with more than 2 reads, this returns `FragmentGeomError::TooManyReads` for them.
+    pub fn to_piscem_string(&self) -> Result<String, FragmentGeomError> {
+        Ok(PiscemGeomDesc::from_fragment(self)?.to_string())
+    }
+
+    /// Builds a `FragmentGeomDesc` from a `piscem` `--geometry` string (e.g.
+    /// `1{b[16]u[12]x:}2{r:}`), as written by [`PiscemGeomDesc`] or
+    /// [`FragmentGeomDesc::to_piscem_string`].  Since the `piscem` format is the canonical
+    /// form of the geometry, this parses `s` exactly as `FragmentGeomDesc::try_from` does,
+    /// except that, as with `to_piscem_string`, more than 2 reads results in
+    /// `FragmentGeomError::TooManyReads`.  Note that the `piscem` string of a geometry
+    /// with tags, homopolymers, spacers, or mismatch tolerances describes them as discards or
+    /// exact anchors, and omits read pairing ids, so only geometries without these survive
+    /// the round trip unchanged.
+    pub fn from_piscem_str(s: &str) -> Result<Self, FragmentGeomError> {
+        let frag_desc = FragmentGeomDesc::try_from(s)?;
+        frag_desc.read_pair()?;
+        Ok(frag_desc)
+    }
+
+    /// Returns the number of non-empty reads (not counting index reads) in this fragment;
+    /// 2 for a typical paired-end geometry such as `1{b[16]u[12]x:}2{r:}`, and 1 for a
+    /// single-end geometry.  This is the number of sequence files a tool should expect.
+    /// A read left empty (e.g. by [`FragmentGeomDesc::without_discards`]) isn't counted.
+    pub fn num_reads(&self) -> usize {
+        self.reads
+            .iter()
+            .filter(|read_desc| !read_desc.is_empty())
+            .count()
+    }
+
+    /// Returns true if, for each read of this geometry, its pieces are a prefix of the
+    /// pieces of the same read of `other` (comparing the pieces with `==`).  For example,
+    /// `1{b[16]}2{r:}` is a prefix of `1{b[16]u[12]x:}2{r:}`.  A read absent from this
+    /// geometry is an (empty) prefix of any read, but `other` must have every read that
+    /// this geometry has.  Index reads, read labels, and the geometry name are ignored.
+    pub fn is_prefix_of(&self, other: &FragmentGeomDesc) -> bool {
+        self.reads.len() <= other.reads.len()
+            && self
+                .reads
+                .iter()
+                .zip(other.reads.iter())
+                .all(|(read, other_read)| other_read.starts_with(read))
+    }
+
+    /// Returns `None` if this geometry and `other` have the same pieces, and otherwise
+    /// the first position at which they differ, scanning the reads in order and then
+    /// the index reads.  A read that is present in only one of the geometries differs
+    /// at index 0 (where the other side is `None`), and a read that is a prefix of the
+    /// corresponding read differs at the position just past its end.  Index reads are
+    /// numbered after the longer of the two read lists, saturating at `u8::MAX`.  Read
+    /// labels and the geometry name are not compared.
+    pub fn diff(&self, other: &FragmentGeomDesc) -> Option<GeomDiff> {
+        let num_reads = self.reads.len().max(other.reads.len());
+        let empty: &[GeomPiece] = &[];
+        let read_pairs = (0..num_reads).map(|i| {
+            (
+                self.reads.get(i).map_or(empty, |r| r.as_slice()),
+                other.reads.get(i).map_or(empty, |r| r.as_slice()),
+            )
+        });
+        let index_pairs = [
+            (self.index1_desc.as_slice(), other.index1_desc.as_slice()),
+            (self.index2_desc.as_slice(), other.index2_desc.as_slice()),
+        ];
+        read_pairs
+            .chain(index_pairs)
+            .enumerate()
+            .find_map(|(i, (left, right))| {
+                first_divergence_single_read(left, right).map(|(index, left, right)| GeomDiff {
+                    read: u8::try_from(i + 1).unwrap_or(u8::MAX),
+                    index,
+                    left,
+                    right,
+                })
+            })
+    }
+
+    /// Appends `pieces` to the end of the description of read 1.  The result is not
+    /// validated; use [`FragmentGeomDesc::validate`] once the geometry is assembled.
+    pub fn extend_read1(&mut self, pieces: impl IntoIterator<Item = GeomPiece>) {
+        self.extend_read(0, pieces);
+    }
+
+    /// Appends `pieces` to the end of the description of read 2, adding a read 2 to a
+    /// single-end fragment if needed.  The result is not validated; use
+    /// [`FragmentGeomDesc::validate`] once the geometry is assembled.
+    pub fn extend_read2(&mut self, pieces: impl IntoIterator<Item = GeomPiece>) {
+        self.extend_read(1, pieces);
+    }
+
+    fn extend_read(&mut self, idx: usize, pieces: impl IntoIterator<Item = GeomPiece>) {
+        if self.reads.len() <= idx {
+            self.reads.resize_with(idx + 1, Vec::new);
+        }
+        self.reads[idx].extend(pieces);
+    }
+
+    /// Returns a new `FragmentGeomDesc` that is a copy of this one, but with the pieces
+    /// of read 1 of `other` appended to the end of its read 1.
+    pub fn concat_read1(&self, other: &FragmentGeomDesc) -> FragmentGeomDesc {
+        let mut concat = self.clone();
+        concat.extend_read1(other.read1_desc().iter().cloned());
+        concat
+    }
+
+    /// Returns true if read `read` (numbered from 1) consists of a single `ReadSeq`
+    /// piece (of any length) and nothing else, as in the common layout where read 2
+    /// is just the cDNA (`2{r:}`).  Returns false if there is no such read.
+    pub fn read_is_pure_readseq(&self, read: u8) -> bool {
+        let Some(read_desc) = (read as usize)
+            .checked_sub(1)
+            .and_then(|i| self.reads.get(i))
+        else {
+            return false;
+        };
+        matches!(read_desc.as_slice(), [GeomPiece::ReadSeq(_, _)])
+    }
+
+    /// Returns false if read `read` (numbered from 1) is absent, empty, or consists solely
+    /// of `Discard` pieces, so that its sequence need not be read at all (e.g. for read 2
+    /// of `1{b[16]u[12]r:}2{x:}`), and true otherwise (e.g. for the common `2{r:}`).
+    pub fn uses_read(&self, read: u8) -> bool {
+        (read as usize)
+            .checked_sub(1)
+            .and_then(|i| self.reads.get(i))
+            .is_some_and(|read_desc| {
+                read_desc
+                    .iter()
+                    .any(|gp| !matches!(gp, GeomPiece::Discard(_)))
+            })
+    }
+
+    /// Returns the canonical representation of the pieces of read `read` (numbered from
+    /// 1), without the read number or enclosing braces (e.g. `b[16]u[12]x:` for read 1 of
+    /// `1{b[16]u[12]x:}2{r:}`), or `None` if there is no such read.
+    pub fn read_string(&self, read: u8) -> Option<String> {
+        let read_desc = self.reads.get((read as usize).checked_sub(1)?)?;
+        Some(read_desc.iter().map(|gp| gp.to_string()).collect())
+    }
+
+    /// Returns the label of read `read` (numbered from 1), or `None` if it is unlabeled.
+    pub fn read_label(&self, read: u8) -> Option<&str> {
+        self.read_labels
+            .get((read as usize).checked_sub(1)?)?
+            .as_deref()
+    }
+
+    /// Sets (or, if `label` is `None`, removes) the label of read `read` (numbered from 1).
+    pub fn set_read_label(&mut self, read: u8, label: Option<String>) {
+        assert!(read > 0, "reads are numbered from 1");
+        let idx = read as usize - 1;
+        if self.read_labels.len() <= idx {
+            self.read_labels.resize(idx + 1, None);
+        }
+        self.read_labels[idx] = label;
+        while let Some(None) = self.read_labels.last() {
+            self.read_labels.pop();
+        }
+    }
+
+    /// Returns the orientation of read `read` (numbered from 1); `Reverse` if it is marked
+    /// as e.g. `2-{r:}`, and `Forward` otherwise (including for a nonexistent read).
+    pub fn read_orientation(&self, read: u8) -> Orientation {
+        (read as usize)
+            .checked_sub(1)
+            .and_then(|i| self.read_orientations.get(i))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Sets the orientation of read `read` (numbered from 1).
+    pub fn set_read_orientation(&mut self, read: u8, orientation: Orientation) {
+        assert!(read > 0, "reads are numbered from 1");
+        let idx = read as usize - 1;
+        if self.read_orientations.len() <= idx {
+            self.read_orientations.resize(idx + 1, Orientation::Forward);
+        }
+        self.read_orientations[idx] = orientation;
+        while let Some(Orientation::Forward) = self.read_orientations.last() {
+            self.read_orientations.pop();
+        }
+    }
+
+    /// Returns the declared sequencing length of read `read` (numbered from 1), as given
+    /// by e.g. `2@150{r:}`, or `None` if no length was declared for it.
+    pub fn read_len_hint(&self, read: u8) -> Option<u32> {
+        *self.read_lens.get((read as usize).checked_sub(1)?)?
+    }
+
+    /// Sets (or, if `read_len` is `None`, removes) the declared sequencing length of read
+    /// `read` (numbered from 1).  This isn't checked against the pieces of the read until
+    /// the geometry is next validated.
+    pub fn set_read_len_hint(&mut self, read: u8, read_len: Option<u32>) {
+        assert!(read > 0, "reads are numbered from 1");
+        let idx = read as usize - 1;
+        if self.read_lens.len() <= idx {
+            self.read_lens.resize(idx + 1, None);
+        }
+        self.read_lens[idx] = read_len;
+        while let Some(None) = self.read_lens.last() {
+            self.read_lens.pop();
+        }
+    }
+
+    /// Returns the description of read 1 of this fragment.
+    pub fn read1_desc(&self) -> &[GeomPiece] {
+        self.reads.first().map_or(&[], |r| r.as_slice())
+    }
+
+    /// Returns the description of read 2 of this fragment, which is
+    /// empty for a single-end fragment.
+    pub fn read2_desc(&self) -> &[GeomPiece] {
+        self.reads.get(1).map_or(&[], |r| r.as_slice())
+    }
+
+    /// Returns the descriptions of read 1 and read 2 of this fragment, for use with
+    /// exporters (e.g. [`PiscemGeomDesc::from_geom_pieces`]) that can describe at most
+    /// a pair of reads.  If this fragment has more than 2 reads, this returns
+    /// `FragmentGeomError::TooManyReads` rather than silently dropping the extra reads.
+    pub fn read_pair(&self) -> Result<(&[GeomPiece], &[GeomPiece]), FragmentGeomError> {
+        if self.reads.len() > 2 {
+            return Err(FragmentGeomError::TooManyReads {
+                found: self.reads.len(),
+                max: 2,
+            });
+        }
+        Ok((self.read1_desc(), self.read2_desc()))
+    }
+
+    /// Returns the location of every cellular barcode segment in the reads of this
+    /// fragment, in read order and then left-to-right.  The start offset of each
+    /// barcode is computed as for [`geom_piece_intervals`], by summing the lengths of
+    /// the pieces that precede it in its read, and the `len` of the barcode is
+    /// reported as-is (so it may be ranged or unbounded).  Since the offset of a
+    /// barcode that follows a ranged or unbounded piece can't be determined, such
+    /// barcodes are omitted.
+    pub fn barcode_segments(&self) -> Vec<BarcodeSpan> {
+        let mut spans = Vec::new();
+        for (i, read_desc) in self.reads.iter().enumerate() {
+            for (offset, gp) in piece_offsets_single_read(read_desc) {
+                if let (Some(start), GeomPiece::Barcode(gl)) = (offset, gp) {
+                    spans.push(BarcodeSpan {
+                        read: (i + 1) as u8,
+                        start,
+                        len: *gl,
+                    });
+                }
+            }
+        }
+        spans
+    }
+
+    /// Returns the `(read, piece_index)` of every cellular barcode segment of this
+    /// fragment, in the order in which they are concatenated to form the combined
+    /// barcode key: read 1 before read 2 (and so on), and left-to-right within each
+    /// read.  Reads are numbered from 1 and piece indices from 0, so for
+    /// `1{b[8]f[ACGT]u[12]}2{b[8]r:}` this is `[(1, 0), (2, 0)]`.  Unlike
+    /// [`FragmentGeomDesc::barcode_segments`], barcodes at an indeterminate offset
+    /// are included.
+    pub fn barcode_concatenation_order(&self) -> Vec<(u8, usize)> {
+        let mut order = Vec::new();
+        for (i, read_desc) in self.reads.iter().enumerate() {
+            for (j, gp) in read_desc.iter().enumerate() {
+                if let GeomPiece::Barcode(_) = gp {
+                    order.push(((i + 1) as u8, j));
+                }
+            }
+        }
+        order
+    }
+
+    /// Returns the `(read, piece_index)` of every biological read segment of this
+    /// fragment that carries a pairing id, grouped by that id: segments are ordered
+    /// by ascending id, and by read and then position among those sharing an id.  So
+    /// for `1{b[16]u[12]r#2:}2{r#1[50]x:}3{r#1:}` this is `[(2, 0), (3, 0), (1, 2)]`.
+    /// Read segments without a pairing id are omitted.
+    pub fn paired_read_segments(&self) -> Vec<(u8, usize)> {
+        let mut segs = Vec::new();
+        for (i, read_desc) in self.reads.iter().enumerate() {
+            for (j, gp) in read_desc.iter().enumerate() {
+                if let GeomPiece::ReadSeq(_, Some(id)) = gp {
+                    segs.push((*id, (i + 1) as u8, j));
+                }
+            }
+        }
+        segs.sort_by_key(|&(id, _, _)| id);
+        segs.into_iter().map(|(_, read, j)| (read, j)).collect()
+    }
+
+    /// Returns the total length of the cellular barcode segments across all reads of
+    /// this fragment; that is, the length of the whitelist key formed by concatenating
+    /// them (e.g. 16 for both `1{b[16]u[12]x:}2{r:}` and `1{b[8]f[ACGT]b[8]u[12]}2{r:}`).
+    /// This is `None` if any barcode segment has a variable length, if there are no
+    /// barcode segments at all, or if their total length doesn't fit in a `u32`.
+    pub fn barcode_key_len(&self) -> Option<u32> {
+        let mut key_len = None;
+        for (_, gp) in self.iter_pieces() {
+            match gp {
+                GeomPiece::Barcode(GeomLen::FixedLen(x)) => {
+                    key_len = Some(key_len.unwrap_or(0_u32).checked_add(*x)?);
+                }
+                GeomPiece::Barcode(_) => return None,
+                _ => {}
+            }
+        }
+        key_len
+    }
+
+    /// Returns the length of the sole UMI segment of this fragment, or `None` if the
+    /// reads of this fragment contain no UMI segment or more than one.
+    pub fn umi_len(&self) -> Option<GeomLen> {
+        sole_geom_len(self.iter_pieces().filter_map(|(_, gp)| match gp {
+            GeomPiece::Umi(gl) => Some(*gl),
+            _ => None,
+        }))
+    }
+
+    /// Returns the length of the sole cellular barcode segment of this fragment, or
+    /// `None` if the reads of this fragment contain no barcode segment or more than one
+    /// (see [`FragmentGeomDesc::barcode_key_len`] for the latter case).
+    pub fn barcode_len(&self) -> Option<GeomLen> {
+        sole_geom_len(self.iter_pieces().filter_map(|(_, gp)| match gp {
+            GeomPiece::Barcode(gl) => Some(*gl),
+            _ => None,
+        }))
+    }
+
+    /// Returns `(read_number, start_offset, sequence)` for each fixed sequence anchor
+    /// in the reads of this fragment, in read order and then left-to-right, where
+    /// `start_offset` is the 0-based offset of the anchor within its read.  As with
+    /// [`FragmentGeomDesc::barcode_segments`], offsets are computed cumulatively, and
+    /// anchors following a ranged or unbounded piece (whose offset is therefore
+    /// indeterminate) are skipped.  Use [`FragmentGeomDesc::contains_fixed_anchor`] to
+    /// check if the fragment contains any anchor at all.
+    pub fn fixed_anchors(&self) -> Vec<(u8, u32, &str)> {
+        let mut anchors = Vec::new();
+        for (i, read_desc) in self.reads.iter().enumerate() {
+            for (offset, gp) in piece_offsets_single_read(read_desc) {
+                if let (Some(start), GeomPiece::Fixed(NucStr::Seq(seq), _)) = (offset, gp) {
+                    anchors.push(((i + 1) as u8, start, seq.as_str()));
+                }
+            }
+        }
+        anchors
+    }
+
+    /// Returns the sequence of each fixed sequence anchor in the reads of this fragment,
+    /// in read order and then left-to-right.  Unlike [`FragmentGeomDesc::fixed_anchors`],
+    /// every anchor is included, whether or not its offset is known.  See
+    /// [`FragmentGeomDesc::distinct_anchor_sequences`] to omit repeated sequences.
+    pub fn anchor_sequences(&self) -> Vec<&str> {
+        self.iter_pieces()
+            .filter_map(|(_, gp)| match gp {
+                GeomPiece::Fixed(NucStr::Seq(seq), _) => Some(seq.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// As [`FragmentGeomDesc::anchor_sequences`], but keeping only the first occurrence
+    /// of each distinct sequence.
+    pub fn distinct_anchor_sequences(&self) -> Vec<&str> {
+        let mut seqs = self.anchor_sequences();
+        let mut seen = std::collections::HashSet::new();
+        seqs.retain(|seq| seen.insert(*seq));
+        seqs
+    }
+
+    /// Returns true if any read of this fragment contains a fixed sequence anchor.
+    pub fn contains_fixed_anchor(&self) -> bool {
+        self.iter_pieces()
+            .any(|(_, gp)| matches!(gp, GeomPiece::Fixed(_, _)))
+    }
+
+    /// Returns the piece of read `read` that covers the base at the 0-based `offset`
+    /// within that read, or `None` if there is no such read or the offset lies past
+    /// the end of a bounded layout.  The pieces are laid out left-to-right, each
+    /// starting where the previous one ends.  A `LenRange` piece is assumed to have its
+    /// minimum length (both for its own containment check and for the placement of the
+    /// pieces after it), while an `Unbounded` or open-ended (`LenRangeFrom`) piece is
+    /// taken to cover every offset from its start onward, since the read may extend
+    /// arbitrarily far into it.
+    pub fn piece_at(&self, read: u8, offset: u32) -> Option<&GeomPiece> {
+        let read_desc = self.reads.get((read as usize).checked_sub(1)?)?;
+        let mut start = 0_u32;
+        for gp in read_desc {
+            let len = match gp.geom_len() {
+                GeomLen::FixedLen(x) | GeomLen::LenRange(x, _) => x,
+                GeomLen::LenRangeFrom(_) | GeomLen::Unbounded => return Some(gp),
+            };
+            // a piece ending past `u32::MAX` covers every remaining offset.
+            match start.checked_add(len) {
+                Some(end) if offset >= end => start = end,
+                _ => return Some(gp),
+            }
+        }
+        None
+    }
+
+    /// Walks the pieces of the reads of this fragment in the order given by
+    /// [`FragmentGeomDesc::iter_pieces`], calling the method of `v` corresponding
+    /// to the type of each.
+    pub fn accept<V: GeomVisitor>(&self, v: &mut V) {
+        for (read, gp) in self.iter_pieces() {
+            match gp {
+                GeomPiece::Barcode(gl) => v.visit_barcode(read, gl),
+                GeomPiece::SampleBarcode(gl) => v.visit_sample_barcode(read, gl),
+                GeomPiece::Umi(gl) => v.visit_umi(read, gl),
+                GeomPiece::Discard(gl) => v.visit_discard(read, gl),
+                GeomPiece::ReadSeq(gl, _) => v.visit_readseq(read, gl),
+                GeomPiece::Fixed(ns, m) => v.visit_fixed(read, ns, *m),
+                GeomPiece::Tag(name, gl) => v.visit_tag(read, name, gl),
+                GeomPiece::Homopolymer(b, gl) => v.visit_homopolymer(read, *b, gl),
+                GeomPiece::Spacer(gl) => v.visit_spacer(read, gl),
+            }
+        }
+    }
+
+    /// Returns true if this geometry and `other` have the same cellular barcode and UMI
+    /// segments, with the same lengths, in the same reads and in the same order, while
+    /// ignoring every other piece; so e.g. `1{b[16]u[12]x:}2{r:}` has the same tag layout
+    /// as `1{b[16]u[12]}2{r[91]}`, but not as `1{u[12]b[16]}2{r:}`.  Since the offsets of
+    /// the segments aren't compared, this only tells whether per-barcode structures (e.g.
+    /// a barcode correction cache) may be shared, not whether the tags can be extracted
+    /// in the same way.  Index reads are not compared.
+    pub fn same_tag_layout(&self, other: &FragmentGeomDesc) -> bool {
+        let tag_pieces = |fg: &FragmentGeomDesc| {
+            fg.iter_pieces()
+                .filter(|(_, gp)| matches!(gp, GeomPiece::Barcode(_) | GeomPiece::Umi(_)))
+                .map(|(read, gp)| (read, gp.clone()))
+                .collect::<Vec<(u8, GeomPiece)>>()
+        };
+        tag_pieces(self) == tag_pieces(other)
+    }
+
+    /// Returns true if this geometry and `other` contain the same pieces, with the same
+    /// multiplicities, regardless of the read they are in or their order within it
+    /// (e.g. `1{b[16]u[12]}2{r:}` and `1{u[12]b[16]r:}`).  This ignores the layout
+    /// of the pieces entirely, and therefore their offsets, so it is only suited to
+    /// fuzzy matching of chemistries, and not to deciding whether two geometries are
+    /// interchangeable.  Index reads are not compared.
+    pub fn piece_multiset_eq(&self, other: &FragmentGeomDesc) -> bool {
+        let mut pieces = self.iter_pieces().map(|(_, gp)| gp).collect::<Vec<_>>();
+        let mut other_pieces = other.iter_pieces().map(|(_, gp)| gp).collect::<Vec<_>>();
+        pieces.sort();
+        other_pieces.sort();
+        pieces == other_pieces
+    }
+
+    /// Returns an iterator over every `GeomPiece` in this fragment, paired with
+    /// the number of the read to which it belongs.  The pieces of read 1 are
+    /// yielded first, in left-to-right order, followed by those of read 2, and so on.
+    /// Index reads are not included.
+    pub fn iter_pieces(&self) -> impl Iterator<Item = (u8, &GeomPiece)> {
+        self.reads
+            .iter()
+            .enumerate()
+            .flat_map(|(i, read_desc)| read_desc.iter().map(move |gp| ((i + 1) as u8, gp)))
+    }
+
+    /// Classifies this geometry by whether any of its pieces (including those of its
+    /// index reads) are fixed sequence anchors, have ranged lengths, or are unbounded.
+    pub fn complexity(&self) -> GeomComplexity {
+        let mut has_anchors = false;
+        let mut has_ranges = false;
+        let mut has_unbounded = false;
+        for gp in self
+            .reads
+            .iter()
+            .flatten()
+            .chain(self.index1_desc.iter())
+            .chain(self.index2_desc.iter())
+        {
+            if let GeomPiece::Fixed(_, _) = gp {
+                has_anchors = true;
+                continue;
+            }
+            match gp.geom_len() {
+                GeomLen::LenRange(_, _) | GeomLen::LenRangeFrom(_) => has_ranges = true,
+                GeomLen::Unbounded => has_unbounded = true,
+                GeomLen::FixedLen(_) => {}
+            }
+        }
+        match (has_anchors, has_ranges, has_unbounded) {
+            (true, true, _) => GeomComplexity::HasAnchorsAndRanges,
+            (true, false, _) => GeomComplexity::HasAnchors,
+            (false, true, _) => GeomComplexity::HasRanges,
+            (false, false, true) => GeomComplexity::FixedWithUnbounded,
+            (false, false, false) => GeomComplexity::FullyFixed,
+        }
+    }
+
+    /// A "complex" geometry is one that contains
+    /// a FixedSeq piece, and/or a BoundedRange piece
+    pub fn is_complex_geometry(&self) -> bool {
+        !matches!(
+            self.complexity(),
+            GeomComplexity::FullyFixed | GeomComplexity::FixedWithUnbounded
+        )
+    }
+
+    /// A "simple" geometry is one that contains only fixed length pieces
+    /// (but doesn't include any fixed seq segments) and unbounded pieces.
+    pub fn is_simple_geometry(&self) -> bool {
+        !self.is_complex_geometry()
+    }
+
+    /// Returns the `KnownChemistry` whose geometry is exactly this one (e.g.
+    /// `ChromiumV3` for `1{b[16]u[12]}2{r:}` or `1{b[16]u[12]x:}2{r:}`), or `None` if
+    /// there is no such chemistry.  The comparison is made structurally, on the pieces
+    /// of each read, so a geometry with index reads never matches.
+    pub fn known_chemistry(&self) -> Option<KnownChemistry> {
+        if self.reads.len() != 2 || !self.index1_desc.is_empty() || !self.index2_desc.is_empty() {
+            return None;
+        }
+        KNOWN_CHEMISTRIES
+            .iter()
+            .find(|(_, r1, r2)| self.reads[0] == *r1 && self.reads[1] == *r2)
+            .map(|(chem, _, _)| *chem)
+    }
+}
+
+/// A builder for constructing a `FragmentGeomDesc` one `GeomPiece` at a time.
+#[derive(Debug, Default)]
+pub struct FragmentGeomDescBuilder {
+    reads: Vec<Vec<GeomPiece>>,
+    index1_desc: Vec<GeomPiece>,
+    index2_desc: Vec<GeomPiece>,
+}
+
+impl FragmentGeomDescBuilder {
+    /// Creates a new builder with no pieces in either read.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `piece` to the end of the description of read `read` (numbered from 1).
+    pub fn push_read(mut self, read: usize, piece: GeomPiece) -> Self {
+        assert!(read > 0, "reads are numbered from 1");
+        if self.reads.len() < read {
+            self.reads.resize_with(read, Vec::new);
+        }
+        self.reads[read - 1].push(piece);
+        self
+    }
+
+    /// Appends `piece` to the end of the description of read 1.
+    pub fn push_read1(self, piece: GeomPiece) -> Self {
+        self.push_read(1, piece)
+    }
+
+    /// Appends `piece` to the end of the description of read 2.
+    pub fn push_read2(self, piece: GeomPiece) -> Self {
+        self.push_read(2, piece)
+    }
+
+    /// Appends `piece` to the end of the description of the first index read.
+    pub fn push_index1(mut self, piece: GeomPiece) -> Self {
+        self.index1_desc.push(piece);
+        self
+    }
+
+    /// Appends `piece` to the end of the description of the second index read.
+    pub fn push_index2(mut self, piece: GeomPiece) -> Self {
+        self.index2_desc.push(piece);
+        self
+    }
+
+    /// Consumes the builder and returns the resulting `FragmentGeomDesc`.  This
+    /// fails if the resulting geometry is not valid according to
+    /// [`FragmentGeomDesc::validate`].
+    pub fn build(self) -> Result<FragmentGeomDesc, FragmentGeomError> {
+        let frag_desc = FragmentGeomDesc {
+            reads: self.reads,
+            read_labels: Vec::new(),
+            read_lens: Vec::new(),
+            read_orientations: Vec::new(),
+            name: None,
+            index1_desc: self.index1_desc,
+            index2_desc: self.index2_desc,
+        };
+        frag_desc.validate()?;
+        Ok(frag_desc)
+    }
+}
+
+/// Parses the FGDL description `input` and returns the resulting `FragmentGeomDesc`
+/// serialized as a JSON string.  Failures (of either parsing or serialization) are
+/// returned as a plain `String` message, so that this can be exposed directly across
+/// an FFI boundary (e.g. to JavaScript via `wasm-bindgen`) without further marshalling.
+#[cfg(feature = "serde")]
+pub fn parse_to_json(input: &str) -> Result<String, String> {
+    let frag_desc = FragmentGeomDesc::try_from(input).map_err(|e| e.to_string())?;
+    serde_json::to_string(&frag_desc).map_err(|e| e.to_string())
+}
+
+/// Parses each line of `input` (e.g. the contents of a file holding one geometry per
+/// line) as an FGDL description, skipping blank lines and lines whose first
+/// non-whitespace character is `#`.  Leading and trailing whitespace on each line is
+/// ignored.  Each result is returned along with the 1-based number of the line it was
+/// parsed from, so that failures can be attributed to the offending line.
+pub fn parse_many(input: &str) -> Vec<(usize, Result<FragmentGeomDesc, FragmentGeomError>)> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(lineno, line)| (lineno, FragmentGeomDesc::try_from(line)))
+        .collect()
+}
+
+/// Returns a human-readable description of the grammar rule `r`, for use
+/// in diagnostics.
+fn describe_rule(r: &Rule) -> String {
+    match r {
+        Rule::EOI => "end of input".to_string(),
+        Rule::fixed_segment => "fixed-length segment".to_string(),
+        Rule::fixed_seq_segment => "fixed sequence segment".to_string(),
+        Rule::ranged_segment => "ranged segment".to_string(),
+        Rule::unbounded_segment => "unbounded segment".to_string(),
+        Rule::numbered_read_desc | Rule::read_num => "read description".to_string(),
+        Rule::frag_name => "geometry name".to_string(),
+        Rule::index_1_desc => "index read 1 description".to_string(),
+        Rule::index_2_desc => "index read 2 description".to_string(),
+        r => format!("{:?}", r).replace('_', " "),
+    }
+}
+
+/// Joins a list of rule descriptions as "a", "a or b", or "a, b, or c".
+fn join_rule_descriptions(rules: &[Rule]) -> String {
+    let descs = rules.iter().map(describe_rule).collect::<Vec<String>>();
+    match descs.len() {
+        0 => String::new(),
+        1 => descs[0].clone(),
+        2 => format!("{} or {}", descs[0], descs[1]),
+        n => format!("{}, or {}", descs[..n - 1].join(", "), descs[n - 1]),
+    }
+}
+
+/// Renders the error `e`, encountered when parsing the geometry description `input`,
+/// as a short diagnostic.  The diagnostic consists of the offending line of the input,
+/// followed by a line with a caret (`^`) under the offending column and a single-line
+/// message, e.g.
+///
+/// ```text
+/// 1{b[16]v[3]u[12]x:}2{r:}
+///        ^ expected fixed sequence segment, fixed-length segment, ranged segment, or unbounded segment at column 8
+/// ```
+pub fn format_parse_error(input: &str, e: &pest::error::Error<Rule>) -> String {
+    let (line_num, col) = match e.line_col {
+        pest::error::LineColLocation::Pos(lc) => lc,
+        pest::error::LineColLocation::Span(lc, _) => lc,
+    };
+    let line = input.lines().nth(line_num - 1).unwrap_or(input);
+    let msg = match &e.variant {
+        pest::error::ErrorVariant::ParsingError {
+            positives,
+            negatives,
+        } => match (positives.is_empty(), negatives.is_empty()) {
+            (false, true) => format!("expected {}", join_rule_descriptions(positives)),
+            (true, false) => format!("unexpected {}", join_rule_descriptions(negatives)),
+            (false, false) => format!(
+                "unexpected {}; expected {}",
+                join_rule_descriptions(negatives),
+                join_rule_descriptions(positives)
+            ),
+            (true, true) => "unknown parsing error".to_string(),
+        },
+        pest::error::ErrorVariant::CustomError { message } => message.clone(),
+    };
+    format!(
+        "{}\n{}^ {} at column {}",
+        line,
+        " ".repeat(col.saturating_sub(1)),
+        msg,
+        col
+    )
+}
+
+/// Checks that the braces and brackets of the description `arg` are balanced, and
+/// not nested within one another in ways the grammar never allows, so that such
+/// mistakes are reported as `FragmentGeomError::UnbalancedBraces` rather than as a
+/// less obvious parse failure.  Quoted read labels are skipped, and a brace pair
+/// within brackets is taken to be the `{n}` repetition count of a fixed sequence.
+fn check_brace_balance(arg: &str) -> Result<(), FragmentGeomError> {
+    let mut block = 0_u8;
+    let mut in_brace = false;
+    let mut in_bracket = false;
+    let mut in_quote = false;
+    // whether we are within a `{n}` repetition count of a fixed sequence
+    let mut in_count = false;
+    let unbalanced = |block: u8| FragmentGeomError::UnbalancedBraces { read: block.max(1) };
+    for c in arg.chars() {
+        if in_quote {
+            in_quote = c != '"';
+            continue;
+        }
+        match c {
+            '"' => in_quote = true,
+            '{' if in_bracket => {
+                if in_count {
+                    return Err(unbalanced(block));
+                }
+                in_count = true;
+            }
+            '}' if in_bracket => {
+                if !in_count {
+                    return Err(unbalanced(block));
+                }
+                in_count = false;
+            }
+            '{' => {
+                if in_brace {
+                    return Err(unbalanced(block));
+                }
+                block = block.saturating_add(1);
+                in_brace = true;
+            }
+            '}' => {
+                if !in_brace || in_bracket {
+                    return Err(unbalanced(block));
+                }
+                in_brace = false;
+            }
+            '[' => {
+                if in_bracket {
+                    return Err(unbalanced(block));
+                }
+                in_bracket = true;
+            }
+            ']' => {
+                if !in_bracket || in_count {
+                    return Err(unbalanced(block));
+                }
+                in_bracket = false;
+            }
+            _ => {}
+        }
+    }
+    if in_brace || in_bracket {
+        return Err(unbalanced(block));
+    }
+    Ok(())
+}
+
+/// Parse the description of a single read.  It's expected that this function is called
+/// when the enclosing rule matches a rule description.  In that case, this function is
+/// called with the `into_inner` of that Pair.  The parsed geometry of the input
+/// description is appended to `read_geom`.
+///
+/// If `spans` is provided, then for each parsed piece, the number of the read
+/// (`read_num`), the index of the piece within the read, and the byte range of the
+/// piece within the input are appended to it.
+fn parse_read_description<'i>(
+    read_desc: impl Iterator<Item = pest::iterators::Pair<'i, Rule>>,
+    read_geom: &mut Vec<GeomPiece>,
+    read_num: u8,
+    mut spans: Option<&mut Vec<PieceSpan>>,
+) -> Result<(), FragmentGeomError> {
+    for rd in read_desc {
+        match rd.as_rule() {
+            Rule::read_desc | Rule::read_pieces_desc => {
+                for geom_piece in rd.into_inner() {
+                    check_segment_numbers(&geom_piece)?;
+                    if let Some(spans) = spans.as_deref_mut() {
+                        let span = geom_piece.as_span();
+                        spans.push((read_num, read_geom.len(), span.start()..span.end()));
+                    }
+                    read_geom.push(parse_segment(geom_piece));
+                }
+            }
+            _ => unreachable!(),
+        };
+    }
+    Ok(())
+}
+
+/// Checks that every length and mismatch count written in the segment `seg` fits in
+/// a `u32`, as the grammar accepts any number of digits.  A number that is too large
+/// results in `FragmentGeomError::ParseFailure` at the position of the number.
+fn check_segment_numbers(seg: &pest::iterators::Pair<Rule>) -> Result<(), FragmentGeomError> {
+    for p in seg.clone().into_inner().flatten() {
+        if matches!(p.as_rule(), Rule::single_len | Rule::max_mismatch)
+            && p.as_str().parse::<u32>().is_err()
+        {
+            return Err(FragmentGeomError::ParseFailure {
+                pos: p.as_span().start(),
+                msg: format!(
+                    "The number {} in the segment {} is too large",
+                    p.as_str(),
+                    seg.as_str()
+                ),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Returns the offset into the input at which the parse error `e` occurred.
+fn parse_error_pos(e: &pest::error::Error<Rule>) -> usize {
+    match e.location {
+        pest::error::InputLocation::Pos(p) => p,
+        pest::error::InputLocation::Span((s, _)) => s,
+    }
+}
+
+/// Parses the piece string `field` (e.g. `b[16]u[12]x:`) of read `read_num` on its
+/// own, without the surrounding read block braces.
+fn parse_read_field(field: &str, read_num: u8) -> Result<Vec<GeomPiece>, FragmentGeomError> {
+    let pairs = FragGeomParser::parse(Rule::read_pieces, field).map_err(|e| {
+        FragmentGeomError::ParseFailure {
+            pos: parse_error_pos(&e),
+            msg: format_parse_error(field, &e),
+        }
+    })?;
+    let mut read_geom = Vec::new();
+    parse_read_description(
+        pairs.filter(|p| p.as_rule() == Rule::read_pieces_desc),
+        &mut read_geom,
+        read_num,
+        None,
+    )?;
+    Ok(read_geom)
+}
+
+impl<'a> TryFrom<&'a str> for FragmentGeomDesc {
+    type Error = FragmentGeomError;
+
+    /// This is the main entry point to obtain a `FragmentGeomDesc` structure.
+    /// This function parses the FGDL description string provided as `arg`, and
+    /// returns either `Ok(FragGeomDesc)`, if the parse is succesful or a
+    /// `FragmentGeomError` describing why the parsing failed.
+    ///
+    /// The description string describes the fragment geometry as a sequence of read
+    /// blocks `1{...}2{...}...`, which must be numbered consecutively starting from 1;
+    /// read `N` is stored at position `N-1` of `reads`.  If the description contains only
+    /// a read 1 block, the `read2_desc()` of the resulting `FragmentGeomDesc` will be empty.  Each read
+    /// number may be followed by a `-`, marking the read as reverse-oriented (see
+    /// [`FragmentGeomDesc::read_orientation`]), and then by the declared sequencing
+    /// length of the read, as in `2-@150{r:}` (see [`FragmentGeomDesc::read_len_hint`]).  The read blocks may optionally be followed by
+    /// `i1{...}` and `i2{...}` blocks describing the index reads of the fragment.
+    fn try_from(arg: &'a str) -> Result<Self, Self::Error> {
+        FragmentGeomDesc::try_from_with_opts(arg, ParseOpts::default())
+    }
+}
+
+impl TryFrom<String> for FragmentGeomDesc {
+    type Error = FragmentGeomError;
+
+    /// Parses the FGDL description held in the owned string `arg`; see the
+    /// `TryFrom<&str>` implementation for details.
+    fn try_from(arg: String) -> Result<Self, Self::Error> {
+        Self::try_from(arg.as_str())
+    }
+}
+
+impl FromStr for FragmentGeomDesc {
+    type Err = FragmentGeomError;
+
+    /// Parses the FGDL description `s`, so that a `FragmentGeomDesc` can be
+    /// obtained with `str::parse`; see the `TryFrom<&str>` implementation for details.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from(s)
+    }
+}
+
+impl FromStr for GeomPiece {
+    type Err = FragmentGeomError;
+
+    /// Parses the description `s` of a single piece (e.g. `b[16]`, `f[ACGT]`, or `r:`),
+    /// in isolation from any fragment.  The piece is checked as it would be within a
+    /// fragment, so e.g. `b[0]` and `b[12-10]` are rejected.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut pairs = FragGeomParser::parse(Rule::piece_desc, s).map_err(|e| {
+            FragmentGeomError::ParseFailure {
+                pos: parse_error_pos(&e),
+                msg: format_parse_error(s, &e),
+            }
+        })?;
+        let seg = pairs.next().unwrap();
+        check_segment_numbers(&seg)?;
+        let gp = parse_segment(seg);
+        validate_read(std::slice::from_ref(&gp), 1)?;
+        Ok(gp)
+    }
+}
+
+// ======== for property testing
+
+/// Returns a template piece of an arbitrary (length-carrying) type, whose length
+/// is meant to be replaced with [`GeomPiece::with_len`].
+#[cfg(feature = "proptest")]
+fn arb_piece_kind() -> impl proptest::strategy::Strategy<Value = GeomPiece> {
+    use proptest::prelude::*;
+    let tag_chars: Vec<char> = ('a'..='z')
+        .chain('A'..='Z')
+        .chain('0'..='9')
+        .chain(['_'])
+        .collect();
+    prop_oneof![
+        Just(GeomPiece::Barcode(GeomLen::Unbounded)),
+        Just(GeomPiece::SampleBarcode(GeomLen::Unbounded)),
+        Just(GeomPiece::Umi(GeomLen::Unbounded)),
+        Just(GeomPiece::Discard(GeomLen::Unbounded)),
+        Just(GeomPiece::ReadSeq(GeomLen::Unbounded, None)),
+        prop::collection::vec(prop::sample::select(tag_chars), 1..6)
+            .prop_map(|n| GeomPiece::Tag(n.into_iter().collect(), GeomLen::Unbounded)),
+        prop::sample::select(b"ACGT".to_vec())
+            .prop_map(|b| GeomPiece::Homopolymer(b, GeomLen::Unbounded)),
+        Just(GeomPiece::Spacer(GeomLen::Unbounded)),
+    ]
+}
+
+/// Returns a piece of arbitrary type with a fixed length.
+#[cfg(feature = "proptest")]
+fn arb_fixed_len_piece() -> impl proptest::strategy::Strategy<Value = GeomPiece> {
+    use proptest::prelude::*;
+    (arb_piece_kind(), 1..40_u32).prop_map(|(gp, x)| gp.with_len(GeomLen::FixedLen(x)))
+}
+
+/// Returns a piece of arbitrary type with a ranged, open-ended, or unbounded length.
+/// Ranges always have distinct bounds, since `l-l` is parsed as a fixed length.
+#[cfg(feature = "proptest")]
+fn arb_variable_len_piece() -> impl proptest::strategy::Strategy<Value = GeomPiece> {
+    use proptest::prelude::*;
+    let gl = prop_oneof![
+        Just(GeomLen::Unbounded),
+        (1..30_u32).prop_map(GeomLen::LenRangeFrom),
+        (1..30_u32, 1..30_u32).prop_map(|(l, d)| GeomLen::LenRange(l, l + d)),
+    ];
+    (arb_piece_kind(), gl).prop_map(|(gp, gl)| gp.with_len(gl))
+}
+
+/// Returns a fixed sequence anchor over the IUPAC alphabet, with a small mismatch
+/// tolerance that is less than its length.
+#[cfg(feature = "proptest")]
+fn arb_fixed_seq_piece() -> impl proptest::strategy::Strategy<Value = GeomPiece> {
+    use proptest::prelude::*;
+    (
+        prop::collection::vec(prop::sample::select(b"ACGTURYSWKMBDHVN".to_vec()), 1..12),
+        0..3_u32,
+    )
+        .prop_map(|(seq, m)| {
+            let m = m.min(seq.len() as u32 - 1);
+            GeomPiece::Fixed(NucStr::Seq(String::from_utf8(seq).unwrap()), m)
+        })
+}
+
+/// Returns the pieces of a single valid read.  A read is a sequence of blocks, each
+/// of which is either a single fixed-length piece or anchor, or a variable-length
+/// piece followed by any number of fixed-length pieces and then an anchor that
+/// delimits it, optionally followed by one trailing variable-length piece.
+#[cfg(feature = "proptest")]
+fn arb_read_desc() -> impl proptest::strategy::Strategy<Value = Vec<GeomPiece>> {
+    use proptest::prelude::*;
+    let block = prop_oneof![
+        prop_oneof![arb_fixed_len_piece(), arb_fixed_seq_piece()].prop_map(|gp| vec![gp]),
+        (
+            arb_variable_len_piece(),
+            prop::collection::vec(arb_fixed_len_piece(), 0..3),
+            arb_fixed_seq_piece(),
+        )
+            .prop_map(|(var, fixed, anchor)| {
+                let mut pieces = vec![var];
+                pieces.extend(fixed);
+                pieces.push(anchor);
+                pieces
+            }),
+    ];
+    prop_oneof![
+        (
+            prop::collection::vec(block, 1..4),
+            proptest::option::of(arb_variable_len_piece()),
+        )
+            .prop_map(|(blocks, trailing)| {
+                let mut pieces: Vec<GeomPiece> = blocks.into_iter().flatten().collect();
+                pieces.extend(trailing);
+                pieces
+            }),
+        arb_variable_len_piece().prop_map(|gp| vec![gp]),
+    ]
+}
+
+/// Returns a [`proptest`](https://docs.rs/proptest) strategy generating arbitrary
+/// valid `FragmentGeomDesc`s, with between 1 and 3 reads, optional index reads, read
+/// labels, and geometry name.  Every generated geometry passes
+/// [`FragmentGeomDesc::validate`], and its `Display` parses back to an equal geometry.
+/// This is only available with the `proptest` feature.
+#[cfg(feature = "proptest")]
+pub fn arb_frag_geom_desc() -> impl proptest::strategy::Strategy<Value = FragmentGeomDesc> {
+    use proptest::prelude::*;
+    let label_chars: Vec<char> = ('a'..='z')
+        .chain('0'..='9')
+        .chain([' ', '_', '.'])
+        .collect();
+    let name_chars: Vec<char> = ('a'..='z')
+        .chain('0'..='9')
+        .chain(['_', '-', '.'])
+        .collect();
+    let label = prop::collection::vec(prop::sample::select(label_chars), 0..8)
+        .prop_map(|l| l.into_iter().collect::<String>());
+    let name = prop::collection::vec(prop::sample::select(name_chars), 1..8)
+        .prop_map(|n| n.into_iter().collect::<String>());
+    (
+        prop::collection::vec((arb_read_desc(), proptest::option::of(label)), 1..4),
+        proptest::option::of(name),
+        proptest::option::of(arb_read_desc()),
+        proptest::option::of(arb_read_desc()),
+    )
+        .prop_map(|(reads, name, index1_desc, index2_desc)| {
+            let (reads, mut read_labels): (Vec<_>, Vec<_>) = reads.into_iter().unzip();
+            while let Some(None) = read_labels.last() {
+                read_labels.pop();
+            }
+            FragmentGeomDesc {
+                reads,
+                read_labels,
+                read_lens: Vec::new(),
+                read_orientations: Vec::new(),
+                name,
+                index1_desc: index1_desc.unwrap_or_default(),
+                index2_desc: index2_desc.unwrap_or_default(),
+            }
+        })
 }
@@ -1,4 +1,12 @@
-use seq_geom_parser::{FragmentGeomDesc, PiscemGeomDesc, SalmonSeparateGeomDesc};
+use seq_geom_parser::{
+    geom_piece_intervals, geom_piece_intervals_with_coords, parse_many, Alphabet, AppendToCmdArgs,
+    BarcodeSpan, BustoolsGeomDesc, CellRangerGeomDesc, CoordBase, Coordinates, EndKind,
+    FlexiplexGeomDesc, FragmentGeomDesc, FragmentGeomDescBuilder, FragmentGeomError,
+    GeomComplexity, GeomDescFormatter, GeomDiff, GeomIntervals, GeomLen, GeomLenBounds, GeomPiece,
+    GeomVisitor, KnownChemistry, NucStr, Orientation, ParseOpts, PieceCounts, PiscemGeomDesc,
+    SalmonSeparateGeomDesc, SegmentKind, StarsoloGeomDesc, UmiToolsGeomDesc,
+};
+use std::collections::HashSet;
 
 /// Parsing a simple format (10xV3 in this case) should work.
 /// We check this by ensuring that the format description makes the
@@ -58,7 +66,7 @@ fn test_parse_piscem_simple() {
     match FragmentGeomDesc::try_from(arg) {
         Ok(frag_desc) => {
             let piscem_desc =
-                PiscemGeomDesc::from_geom_pieces(&frag_desc.read1_desc, &frag_desc.read2_desc);
+                PiscemGeomDesc::from_geom_pieces(frag_desc.read1_desc(), frag_desc.read2_desc());
 
             assert_eq!(
                 piscem_desc,
@@ -80,7 +88,7 @@ fn test_parse_piscem_complex() {
     match FragmentGeomDesc::try_from(arg) {
         Ok(frag_desc) => {
             let piscem_desc =
-                PiscemGeomDesc::from_geom_pieces(&frag_desc.read1_desc, &frag_desc.read2_desc);
+                PiscemGeomDesc::from_geom_pieces(frag_desc.read1_desc(), frag_desc.read2_desc());
 
             assert_eq!(
                 piscem_desc,
@@ -106,9 +114,10 @@ fn test_salmon_simple() {
     match FragmentGeomDesc::try_from(arg) {
         Ok(frag_desc) => {
             let salmon_desc = SalmonSeparateGeomDesc::from_geom_pieces(
-                &frag_desc.read1_desc,
-                &frag_desc.read2_desc,
-            );
+                frag_desc.read1_desc(),
+                frag_desc.read2_desc(),
+            )
+            .expect("should convert");
 
             assert_eq!(
                 salmon_desc,
@@ -164,3 +173,2653 @@ fn test_fail_on_superfluous_input() {
         Err(_e) => {}
     };
 }
+
+/// Parsed geometries should compare structurally, so that a parse can be
+/// checked directly against the expected pieces, and so that identical
+/// geometries collapse when placed in a `HashSet`.
+#[test]
+fn test_geom_eq_and_hash() {
+    let arg = "1{b[9-10]f[ACCGT]u[12]b[10]}2{r:}";
+    let frag_desc = FragmentGeomDesc::try_from(arg).expect("should parse");
+    let expected = FragmentGeomDesc {
+        reads: vec![
+            vec![
+                GeomPiece::Barcode(GeomLen::LenRange(9, 10)),
+                GeomPiece::Fixed(NucStr::Seq("ACCGT".to_string()), 0),
+                GeomPiece::Umi(GeomLen::FixedLen(12)),
+                GeomPiece::Barcode(GeomLen::FixedLen(10)),
+            ],
+            vec![GeomPiece::ReadSeq(GeomLen::Unbounded, None)],
+        ],
+        read_labels: vec![],
+        read_lens: vec![],
+        read_orientations: vec![],
+        name: None,
+        index1_desc: vec![],
+        index2_desc: vec![],
+    };
+    assert_eq!(frag_desc, expected);
+
+    let mut geoms = HashSet::new();
+    geoms.insert(frag_desc);
+    geoms.insert(FragmentGeomDesc::try_from(arg).expect("should parse"));
+    geoms.insert(FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse"));
+    assert_eq!(geoms.len(), 2);
+}
+
+/// The length bounds of each read should account for fixed, ranged,
+/// anchor, and unbounded pieces.
+#[test]
+fn test_total_len_range() {
+    let frag_desc =
+        FragmentGeomDesc::try_from("1{b[9-10]f[ACCGT]u[12]b[10]}2{r:}").expect("should parse");
+    assert_eq!(
+        frag_desc.total_len_range(),
+        (
+            GeomLenBounds {
+                min: 36,
+                max: Some(37)
+            },
+            GeomLenBounds { min: 0, max: None }
+        )
+    );
+}
+
+/// Iterating over the pieces of a fragment should visit read 1 and then
+/// read 2, tagging each piece with its read number.
+#[test]
+fn test_iter_pieces() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]}2{x:r[20]f[GTTTAAGAGCTAAGCTGGAA]x:}")
+        .expect("should parse");
+    let read_nums = frag_desc.iter_pieces().map(|(r, _)| r).collect::<Vec<u8>>();
+    assert_eq!(read_nums, vec![1, 1, 2, 2, 2, 2]);
+
+    let first_anchor = frag_desc
+        .iter_pieces()
+        .find(|(_, gp)| matches!(gp, GeomPiece::Fixed(_, _)));
+    assert_eq!(
+        first_anchor,
+        Some((
+            2,
+            &GeomPiece::Fixed(NucStr::Seq("GTTTAAGAGCTAAGCTGGAA".to_string()), 0)
+        ))
+    );
+}
+
+/// With the `serde` feature enabled, a parsed geometry should make the
+/// round trip through JSON and compare equal to the original.
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_round_trip() {
+    let frag_desc =
+        FragmentGeomDesc::try_from("1{b[9-10]f[ACCGT]u[12]b[10]}2{r:}").expect("should parse");
+    let json = serde_json::to_string(&frag_desc).expect("should serialize");
+    assert!(json.contains(r#"{"LenRange":[9,10]}"#));
+    let de_desc: FragmentGeomDesc = serde_json::from_str(&json).expect("should deserialize");
+    assert_eq!(frag_desc, de_desc);
+}
+
+/// Parsing a single-end format (one having only a read 1 block) should work,
+/// yielding an empty read 2, and should round trip without a read 2 block.
+#[test]
+fn test_parse_format_single_end() {
+    let arg = "1{b[16]u[12]}";
+    match FragmentGeomDesc::try_from(arg) {
+        Ok(frag_desc) => {
+            assert!(frag_desc.read2_desc().is_empty());
+            assert_eq!(arg, format!("{}", frag_desc));
+        }
+        Err(e) => {
+            panic!("Failed to parse geometry {}", e);
+        }
+    };
+}
+
+/// Building a geometry programmatically should produce the same result as
+/// parsing it, and invalid pieces should be rejected at construction time.
+#[test]
+fn test_build_geom() {
+    let frag_desc = FragmentGeomDescBuilder::new()
+        .push_read1(GeomPiece::Barcode(GeomLen::FixedLen(16)))
+        .push_read1(GeomPiece::Umi(GeomLen::FixedLen(12)))
+        .push_read1(GeomPiece::Discard(GeomLen::Unbounded))
+        .push_read2(GeomPiece::ReadSeq(GeomLen::Unbounded, None))
+        .build()
+        .expect("should build");
+    assert_eq!(
+        frag_desc,
+        FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse")
+    );
+
+    assert!(FragmentGeomDesc::new(
+        vec![GeomPiece::Barcode(GeomLen::LenRange(12, 10))],
+        vec![GeomPiece::ReadSeq(GeomLen::Unbounded, None)]
+    )
+    .is_err());
+    assert!(FragmentGeomDesc::new(
+        vec![GeomPiece::Fixed(NucStr::Seq("ACGZ".to_string()), 0)],
+        vec![GeomPiece::ReadSeq(GeomLen::Unbounded, None)]
+    )
+    .is_err());
+    assert!(
+        FragmentGeomDesc::new(vec![], vec![GeomPiece::ReadSeq(GeomLen::Unbounded, None)]).is_err()
+    );
+}
+
+/// The 10x v2 and v3 layouts should produce the expected 1-based
+/// STARsolo barcode and UMI offsets.
+#[test]
+fn test_starsolo_10x() {
+    let v2 = FragmentGeomDesc::try_from("1{b[16]u[10]x:}2{r:}").expect("should parse");
+    let starsolo_desc = StarsoloGeomDesc::from_geom_pieces(v2.read1_desc(), v2.read2_desc())
+        .expect("should convert");
+    assert_eq!(
+        starsolo_desc,
+        StarsoloGeomDesc {
+            cb_start: 1,
+            cb_len: 16,
+            umi_start: 17,
+            umi_len: 10
+        }
+    );
+
+    let v3 = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    let starsolo_desc = StarsoloGeomDesc::from_geom_pieces(v3.read1_desc(), v3.read2_desc())
+        .expect("should convert");
+    assert_eq!(
+        starsolo_desc,
+        StarsoloGeomDesc {
+            cb_start: 1,
+            cb_len: 16,
+            umi_start: 17,
+            umi_len: 12
+        }
+    );
+}
+
+/// Geometries that STARsolo can't express should produce an error.
+#[test]
+fn test_starsolo_unsupported() {
+    for arg in [
+        "1{b[9-10]f[ACCGT]u[12]b[10]}2{r:}",
+        "1{b[8]x[4]b[8]u[12]}2{r:}",
+        "1{b[16]}2{r:}",
+    ] {
+        let frag_desc = FragmentGeomDesc::try_from(arg).expect("should parse");
+        assert!(
+            StarsoloGeomDesc::from_geom_pieces(frag_desc.read1_desc(), frag_desc.read2_desc())
+                .is_err()
+        );
+    }
+}
+
+/// The 10x v3 layout should produce the standard kallisto bus technology string.
+#[test]
+fn test_bustools_simple() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    let bustools_desc =
+        BustoolsGeomDesc::from_geom_pieces(frag_desc.read1_desc(), frag_desc.read2_desc())
+            .expect("should convert");
+    assert_eq!(format!("{}", bustools_desc), "0,0,16:0,16,28:1,0,0");
+}
+
+/// Fixed sequence anchors and ranged lengths can't be expressed in the
+/// kallisto bus technology string and should produce an error.
+#[test]
+fn test_bustools_unsupported() {
+    for arg in [
+        "1{b[9-10]f[ACCGT]u[12]b[10]}2{r:}",
+        "1{b[16]u[12]}2{x:r[20]f[GTTTAAGAGCTAAGCTGGAA]x:}",
+    ] {
+        let frag_desc = FragmentGeomDesc::try_from(arg).expect("should parse");
+        assert!(
+            BustoolsGeomDesc::from_geom_pieces(frag_desc.read1_desc(), frag_desc.read2_desc())
+                .is_err()
+        );
+    }
+}
+
+/// The tool-agnostic intervals should account for discarded and anchor
+/// sequence, and mark a trailing unbounded read as running to the end.
+#[test]
+fn test_geom_piece_intervals() {
+    let frag_desc =
+        FragmentGeomDesc::try_from("1{b[16]x[2]f[ACG]u[12]r:}2{r:}").expect("should parse");
+    let intervals = geom_piece_intervals(frag_desc.read1_desc()).expect("should compute intervals");
+    assert_eq!(
+        intervals,
+        GeomIntervals {
+            barcode: vec![(1, Some(16))],
+            umi: vec![(22, Some(33))],
+            sample_barcode: vec![],
+            tag: vec![],
+            read: vec![(34, None)],
+            spacer: vec![],
+        }
+    );
+
+    let frag_desc =
+        FragmentGeomDesc::try_from("1{b[9-10]f[ACCGT]u[12]b[10]}2{r:}").expect("should parse");
+    assert!(geom_piece_intervals(frag_desc.read1_desc()).is_err());
+}
+
+/// Parsing a format with index reads should work, and the index reads
+/// should make the round trip through parsing and back through printing.
+#[test]
+fn test_parse_format_index_reads() {
+    let arg = "1{b[16]u[12]x:}2{r:}i1{b[8]}i2{b[8]}";
+    match FragmentGeomDesc::try_from(arg) {
+        Ok(frag_desc) => {
+            assert_eq!(
+                frag_desc.index1_desc,
+                vec![GeomPiece::Barcode(GeomLen::FixedLen(8))]
+            );
+            assert_eq!(
+                frag_desc.index2_desc,
+                vec![GeomPiece::Barcode(GeomLen::FixedLen(8))]
+            );
+            assert_eq!(arg, format!("{}", frag_desc));
+        }
+        Err(e) => {
+            panic!("Failed to parse geometry {}", e);
+        }
+    };
+
+    let arg = "1{b[16]u[12]x:}2{r:}i1{b[10]}";
+    let frag_desc = FragmentGeomDesc::try_from(arg).expect("should parse");
+    assert!(frag_desc.index2_desc.is_empty());
+    assert_eq!(arg, format!("{}", frag_desc));
+}
+
+/// Sample barcodes should parse as a distinct kind of piece, make the
+/// round trip through printing, and be passed through to piscem.
+#[test]
+fn test_parse_sample_barcode() {
+    let arg = "1{b[16]u[12]s[8]x:}2{r:}";
+    let frag_desc = FragmentGeomDesc::try_from(arg).expect("should parse");
+    assert_eq!(
+        frag_desc.read1_desc()[2],
+        GeomPiece::SampleBarcode(GeomLen::FixedLen(8))
+    );
+    assert_eq!(arg, format!("{}", frag_desc));
+    assert!(frag_desc.is_simple_geometry());
+
+    let piscem_desc =
+        PiscemGeomDesc::from_geom_pieces(frag_desc.read1_desc(), frag_desc.read2_desc());
+    assert_eq!(piscem_desc.read1_desc, "{b[16]u[12]s[8]x:}");
+
+    let intervals = geom_piece_intervals(frag_desc.read1_desc()).expect("should compute intervals");
+    assert_eq!(intervals.sample_barcode, vec![(29, Some(36))]);
+}
+
+/// Parse failures should be reported as specific `FragmentGeomError`
+/// variants, which should still convert into an `anyhow::Error`.
+#[test]
+fn test_parse_error_kinds() {
+    match FragmentGeomDesc::try_from("1{b[16]v[3]u[12]x:}2{r:}") {
+        Err(FragmentGeomError::ParseFailure { pos, .. }) => assert_eq!(pos, 7),
+        r => panic!("expected a parse failure, but got {:?}", r),
+    };
+
+    assert_eq!(
+        FragmentGeomDesc::new(vec![], vec![GeomPiece::ReadSeq(GeomLen::Unbounded, None)]),
+        Err(FragmentGeomError::MissingRead(1))
+    );
+
+    let as_anyhow = || -> anyhow::Result<FragmentGeomDesc> {
+        Ok(FragmentGeomDesc::try_from("1{b[16]v[3]u[12]x:}2{r:}")?)
+    };
+    assert!(as_anyhow().is_err());
+}
+
+/// A range whose lower bound exceeds its upper bound should be rejected,
+/// while a range whose bounds are equal should be normalized to a fixed length.
+#[test]
+fn test_parse_range_bounds() {
+    match FragmentGeomDesc::try_from("1{b[12-10]}2{r:}") {
+        Err(FragmentGeomError::InvalidRange { segment, low, high }) => {
+            assert_eq!(segment, "b[12-10]");
+            assert_eq!((low, high), (12, 10));
+        }
+        r => panic!("expected an invalid range, but got {:?}", r),
+    };
+
+    let frag_desc = FragmentGeomDesc::try_from("1{b[10-10]}2{r:}").expect("should parse");
+    assert_eq!(
+        frag_desc.read1_desc(),
+        vec![GeomPiece::Barcode(GeomLen::FixedLen(10))]
+    );
+    assert_eq!(format!("{}", frag_desc), "1{b[10]}2{r:}");
+}
+
+/// Fixed sequences may contain IUPAC ambiguity codes, which should make
+/// the round trip, and should match any of the bases they represent.
+#[test]
+fn test_parse_iupac_fixed_seq() {
+    let arg = "1{b[16]u[12]}2{x:r[20]f[GTTNAAGRGCTAAGCTGGAA]x:}";
+    let frag_desc = FragmentGeomDesc::try_from(arg).expect("should parse");
+    assert_eq!(arg, format!("{}", frag_desc));
+
+    let anchor = NucStr::Seq("ACGNT".to_string());
+    assert!(anchor.is_ambiguous());
+    assert!(anchor.matches(b"ACGAT"));
+    assert!(anchor.matches(b"ACGGT"));
+    assert!(!anchor.matches(b"ACCAT"));
+    assert!(!anchor.matches(b"ACGA"));
+
+    let anchor = NucStr::Seq("ACGT".to_string());
+    assert!(!anchor.is_ambiguous());
+    assert!(anchor.matches(b"ACGT"));
+    assert!(!anchor.matches(b"ACGA"));
+
+    assert!(FragmentGeomDesc::try_from("1{b[16]f[ACGZ]}2{r:}").is_err());
+
+    // piscem passes ambiguous anchors through, while salmon rejects them
+    let piscem_desc = PiscemGeomDesc::from_fragment(&frag_desc).expect("should convert");
+    assert_eq!(piscem_desc.to_string(), arg);
+    assert!(SalmonSeparateGeomDesc::from_fragment(&frag_desc).is_err());
+}
+
+/// The fixed prefix of each read should stop at the first ranged or
+/// unbounded piece.
+#[test]
+fn test_fixed_prefix_len() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    assert_eq!(frag_desc.fixed_prefix_len(), (28, 0));
+
+    let frag_desc = FragmentGeomDesc::try_from("1{b[9-10]f[ACCGT]u[12]b[10]}2{f[ACG]r[20]x:}")
+        .expect("should parse");
+    assert_eq!(frag_desc.fixed_prefix_len(), (0, 23));
+}
+
+/// Reverse complementing a read should reverse its pieces and reverse
+/// complement its anchors, and doing so twice should be the identity.
+#[test]
+fn test_reverse_complement_read() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]}2{x:r[20]f[GTTTAAGAGCTAAGCTGGAA]x:}")
+        .expect("should parse");
+    let rc_desc = frag_desc
+        .reverse_complement_read(2)
+        .expect("should reverse complement");
+    assert_eq!(
+        format!("{}", rc_desc),
+        "1{b[16]u[12]}2{x:f[TTCCAGCTTAGCTCTTAAAC]r[20]x:}"
+    );
+    assert_eq!(rc_desc.reverse_complement_read(2), Ok(frag_desc.clone()));
+    assert_eq!(
+        frag_desc
+            .reverse_complement_read(1)
+            .expect("should reverse complement")
+            .read1_desc(),
+        vec![
+            GeomPiece::Umi(GeomLen::FixedLen(12)),
+            GeomPiece::Barcode(GeomLen::FixedLen(16))
+        ]
+    );
+    assert_eq!(
+        GeomPiece::Fixed(NucStr::Seq("ACGRN".to_string()), 0).reverse_complement(),
+        GeomPiece::Fixed(NucStr::Seq("NYCGT".to_string()), 0)
+    );
+
+    // anchors with any DNA ambiguity code survive a double application.
+    let frag_desc =
+        FragmentGeomDesc::try_from("1{b[16]f[ACGTRYSWKMBDHVN]u[12]}2{r:}").expect("should parse");
+    let twice = frag_desc
+        .reverse_complement_read(1)
+        .and_then(|rc| rc.reverse_complement_read(1));
+    assert_eq!(twice, Ok(frag_desc.clone()));
+
+    // `U` is complemented to `A`, but can't come back, so reads with it are rejected.
+    assert_eq!(
+        NucStr::Seq("ACGU".to_string()).reverse_complement(),
+        NucStr::Seq("ACGT".to_string())
+    );
+    let rna_desc = FragmentGeomDesc::try_from("1{b[16]f[ACGU]u[12]}2{r:}").expect("should parse");
+    assert_eq!(
+        rna_desc.reverse_complement_read(1),
+        Err(FragmentGeomError::InvalidBase {
+            base: 'U',
+            alphabet: Alphabet::Dna
+        })
+    );
+    assert!(rna_desc.reverse_complement_read(2).is_ok());
+
+    assert_eq!(
+        frag_desc.reverse_complement_read(0),
+        Err(FragmentGeomError::MissingRead(0))
+    );
+    assert_eq!(
+        frag_desc.reverse_complement_read(3),
+        Err(FragmentGeomError::MissingRead(3))
+    );
+}
+
+/// Canonicalizing a geometry should merge adjacent discards, so that
+/// equivalent geometries print identically.
+#[test]
+fn test_canonicalize() {
+    let mut frag_desc =
+        FragmentGeomDesc::try_from("1{b[16]x[4]x[4]u[12]x[2]x:}2{r:}").expect("should parse");
+    frag_desc.canonicalize();
+    assert_eq!(format!("{}", frag_desc), "1{b[16]x[8]u[12]x:}2{r:}");
+
+    let mut other = FragmentGeomDesc::try_from("1{b[16]x[8]u[12]x:}2{r:}").expect("should parse");
+    other.canonicalize();
+    assert_eq!(frag_desc, other);
+
+    let mut frag_desc =
+        FragmentGeomDesc::try_from("1{b[16]x[2-4]x[4]f[ACG]}2{r:}").expect("should parse");
+    frag_desc.canonicalize();
+    assert_eq!(format!("{}", frag_desc), "1{b[16]x[6-8]f[ACG]}2{r:}");
+}
+
+/// The piece counts should tally each type of piece across both reads,
+/// as well as per read.
+#[test]
+fn test_piece_counts() {
+    let frag_desc =
+        FragmentGeomDesc::try_from("1{b[9-10]f[ACCGT]u[12]b[10]}2{r:}").expect("should parse");
+    assert_eq!(
+        frag_desc.counts(),
+        PieceCounts {
+            barcode: 2,
+            sample_barcode: 0,
+            umi: 1,
+            readseq: 1,
+            discard: 0,
+            fixed: 1,
+            tag: 0,
+            homopolymer: 0,
+            spacer: 0
+        }
+    );
+    let (r1_counts, r2_counts) = frag_desc.counts_per_read();
+    assert_eq!(r1_counts.barcode, 2);
+    assert_eq!(r1_counts.readseq, 0);
+    assert_eq!(r2_counts.readseq, 1);
+    assert_eq!(r2_counts.barcode, 0);
+}
+
+/// Custom tags should parse with their name, make the round trip through
+/// printing, and be reported with their interval.
+#[test]
+fn test_parse_tag() {
+    let arg = "1{b[16]u[12]t<qual>[6]x:}2{r:}";
+    let frag_desc = FragmentGeomDesc::try_from(arg).expect("should parse");
+    assert_eq!(
+        frag_desc.read1_desc()[2],
+        GeomPiece::Tag("qual".to_string(), GeomLen::FixedLen(6))
+    );
+    assert_eq!(arg, format!("{}", frag_desc));
+
+    let intervals = geom_piece_intervals(frag_desc.read1_desc()).expect("should compute intervals");
+    assert_eq!(intervals.tag, vec![("qual".to_string(), (29, Some(34)))]);
+
+    let piscem_desc =
+        PiscemGeomDesc::from_geom_pieces(frag_desc.read1_desc(), frag_desc.read2_desc());
+    assert_eq!(piscem_desc.read1_desc, "{b[16]u[12]x[6]x:}");
+
+    assert!(FragmentGeomDesc::try_from("1{b[16]t<>[6]}2{r:}").is_err());
+}
+
+/// Geometries that parse, but that make no sense, should be rejected by
+/// validation at parse time.
+#[test]
+fn test_validate_geom() {
+    assert!(matches!(
+        FragmentGeomDesc::try_from("1{b[0]u[12]x:}2{r:}"),
+        Err(FragmentGeomError::ZeroLength { .. })
+    ));
+    assert!(matches!(
+        FragmentGeomDesc::try_from("1{b[16]u[0-12]}2{r:}"),
+        Err(FragmentGeomError::ZeroLength { .. })
+    ));
+    assert!(FragmentGeomDesc::try_from("1{}2{r:}").is_err());
+    assert!(matches!(
+        FragmentGeomDesc::new(vec![], vec![GeomPiece::ReadSeq(GeomLen::Unbounded, None)]),
+        Err(FragmentGeomError::MissingRead(1))
+    ));
+    assert!(matches!(
+        FragmentGeomDesc::new(
+            vec![
+                GeomPiece::Barcode(GeomLen::FixedLen(16)),
+                GeomPiece::Discard(GeomLen::Unbounded),
+                GeomPiece::ReadSeq(GeomLen::Unbounded, None)
+            ],
+            vec![]
+        ),
+        Err(FragmentGeomError::MultipleUnbounded { read: 1 })
+    ));
+    // unbounded pieces separated by an anchor are fine
+    assert!(
+        FragmentGeomDesc::try_from("1{b[16]u[12]}2{x:r[20]f[GTTTAAGAGCTAAGCTGGAA]x:}")
+            .expect("should parse")
+            .validate()
+            .is_ok()
+    );
+}
+
+/// A parse failure should carry a diagnostic that points at the
+/// offending column with a caret.
+#[test]
+fn test_parse_error_diagnostic() {
+    match FragmentGeomDesc::try_from("1{b[16]v[3]u[12]x:}2{r:}") {
+        Err(FragmentGeomError::ParseFailure { msg, .. }) => {
+            assert_eq!(
+                msg,
+                "1{b[16]v[3]u[12]x:}2{r:}\n       ^ expected fixed sequence segment, fixed-length segment, ranged segment, or unbounded segment at column 8"
+            );
+        }
+        r => panic!("expected a parse failure, but got {:?}", r),
+    };
+
+    match FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}3{b[16]v[3]}") {
+        Err(FragmentGeomError::ParseFailure { msg, .. }) => {
+            assert!(msg.ends_with("^ expected fixed sequence segment, fixed-length segment, ranged segment, or unbounded segment at column 28"));
+        }
+        r => panic!("expected a parse failure, but got {:?}", r),
+    };
+}
+
+/// The piscem geometry string should be obtainable in a single call.
+#[test]
+fn test_to_piscem_string() {
+    let frag_desc =
+        FragmentGeomDesc::try_from("1{b[16-18]f[ACG]u[12]x:}2{r:}").expect("should parse");
+    assert_eq!(
+        frag_desc.to_piscem_string().expect("should convert"),
+        "1{b[16-18]f[ACG]u[12]x:}2{r:}"
+    );
+}
+
+/// Reusing a `FragmentGeomDesc` with `parse_into` should yield the
+/// same result as a fresh parse, and should not keep stale pieces.
+#[test]
+fn test_parse_into_reuse() {
+    let mut frag_desc =
+        FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}i1{r[8]}").expect("should parse");
+
+    frag_desc
+        .parse_into("1{b[9-10]f[CAGAGC]u[8]b[10]}")
+        .expect("should parse");
+    assert_eq!(
+        frag_desc,
+        FragmentGeomDesc::try_from("1{b[9-10]f[CAGAGC]u[8]b[10]}").expect("should parse")
+    );
+    assert!(frag_desc.read2_desc().is_empty());
+    assert!(frag_desc.index1_desc.is_empty());
+
+    assert!(frag_desc.parse_into("1{b[16]v[3]}2{r:}").is_err());
+}
+
+/// Fragments may consist of more than two reads, which should round trip,
+/// but can't be handed to exporters that only understand read pairs.
+#[test]
+fn test_multi_read() {
+    let arg = "1{b[16]u[12]x:}2{r:}3{r[50]}";
+    let frag_desc = FragmentGeomDesc::try_from(arg).expect("should parse");
+    assert_eq!(frag_desc.num_reads(), 3);
+    assert_eq!(
+        frag_desc.reads[2],
+        vec![GeomPiece::ReadSeq(GeomLen::FixedLen(50), None)]
+    );
+    assert_eq!(
+        frag_desc.read2_desc(),
+        &[GeomPiece::ReadSeq(GeomLen::Unbounded, None)]
+    );
+    assert_eq!(arg, format!("{}", frag_desc));
+    assert_eq!(
+        frag_desc.iter_pieces().last(),
+        Some((3, &GeomPiece::ReadSeq(GeomLen::FixedLen(50), None)))
+    );
+    assert_eq!(
+        frag_desc.to_piscem_string(),
+        Err(FragmentGeomError::TooManyReads { found: 3, max: 2 })
+    );
+
+    let built = FragmentGeomDescBuilder::new()
+        .push_read1(GeomPiece::Barcode(GeomLen::FixedLen(16)))
+        .push_read1(GeomPiece::Umi(GeomLen::FixedLen(12)))
+        .push_read1(GeomPiece::Discard(GeomLen::Unbounded))
+        .push_read2(GeomPiece::ReadSeq(GeomLen::Unbounded, None))
+        .push_read(3, GeomPiece::ReadSeq(GeomLen::FixedLen(50), None))
+        .build()
+        .expect("should build");
+    assert_eq!(built, frag_desc);
+
+    // read blocks must be numbered consecutively from 1
+    assert_eq!(
+        FragmentGeomDesc::try_from("1{b[16]u[12]x:}3{r:}"),
+        Err(FragmentGeomError::MissingRead(2))
+    );
+    assert!(matches!(
+        FragmentGeomDesc::try_from("1{b[16]u[12]x:}1{r:}"),
+        Err(FragmentGeomError::ParseFailure { .. })
+    ));
+}
+
+/// Read blocks are numbered with a `u8`, so descriptions whose read and index
+/// read blocks number more than 255 should be rejected rather than wrap.
+#[test]
+fn test_read_count_limit() {
+    let reads = |n: usize| {
+        (1..=n)
+            .map(|i| format!("{}{{r[1]}}", i))
+            .collect::<String>()
+    };
+
+    let frag_desc = FragmentGeomDesc::try_from(reads(255).as_str()).expect("should parse");
+    assert_eq!(frag_desc.num_reads(), 255);
+    assert_eq!(frag_desc.read_string(255).as_deref(), Some("r[1]"));
+    assert_eq!(
+        FragmentGeomDesc::try_from(reads(256).as_str()),
+        Err(FragmentGeomError::TooManyReads {
+            found: 256,
+            max: 255
+        })
+    );
+
+    // index reads are numbered after the reads
+    let arg = format!("{}i1{{b[8]}}", reads(254));
+    assert!(FragmentGeomDesc::try_from(arg.as_str()).is_ok());
+    let arg = format!("{}i2{{b[8]}}", reads(254));
+    assert_eq!(
+        FragmentGeomDesc::try_from(arg.as_str()),
+        Err(FragmentGeomError::TooManyReads {
+            found: 256,
+            max: 255
+        })
+    );
+
+    // the same limit applies to geometries that are built directly
+    let builder = || {
+        (1..=255).fold(FragmentGeomDescBuilder::new(), |b, i| {
+            b.push_read(i, GeomPiece::ReadSeq(GeomLen::FixedLen(1), None))
+        })
+    };
+    let built = builder().build().expect("should build");
+    assert_eq!(built.num_reads(), 255);
+    assert_eq!(
+        builder()
+            .push_index1(GeomPiece::Barcode(GeomLen::FixedLen(8)))
+            .build(),
+        Err(FragmentGeomError::TooManyReads {
+            found: 256,
+            max: 255
+        })
+    );
+}
+
+/// Barcode segments should be reported with their 0-based offsets, and
+/// those whose offset can't be determined should be omitted.
+#[test]
+fn test_barcode_segments() {
+    let frag_desc = FragmentGeomDesc::try_from("1{x[2]b[9-10]f[CAGAGC]u[8]b[10]}2{b[4]r:}")
+        .expect("should parse");
+    assert_eq!(
+        frag_desc.barcode_segments(),
+        vec![
+            BarcodeSpan {
+                read: 1,
+                start: 2,
+                len: GeomLen::LenRange(9, 10),
+            },
+            BarcodeSpan {
+                read: 2,
+                start: 0,
+                len: GeomLen::FixedLen(4),
+            },
+        ]
+    );
+
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]f[ACG]u[12]b:}2{r:}").expect("should parse");
+    assert_eq!(
+        frag_desc.barcode_segments()[1],
+        BarcodeSpan {
+            read: 1,
+            start: 31,
+            len: GeomLen::Unbounded,
+        }
+    );
+}
+
+/// Fixed anchors should be reported with their 0-based offsets, skipping
+/// those whose offset is indeterminate.
+#[test]
+fn test_fixed_anchors() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]f[ACGT]u[12]x:f[TTTT]r:}2{x[3]f[CAGAGC]r:}")
+        .expect("should parse");
+    assert!(frag_desc.contains_fixed_anchor());
+    assert_eq!(
+        frag_desc.fixed_anchors(),
+        vec![(1, 16, "ACGT"), (2, 3, "CAGAGC")]
+    );
+
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    assert!(!frag_desc.contains_fixed_anchor());
+    assert!(frag_desc.fixed_anchors().is_empty());
+}
+
+/// Lowercase fixed sequences should be accepted, and normalized to uppercase.
+#[test]
+fn test_lowercase_fixed_seq() {
+    let lower = FragmentGeomDesc::try_from("1{b[16]f[acgtn]u[12]x:}2{r:}").expect("should parse");
+    let upper = FragmentGeomDesc::try_from("1{b[16]f[ACGTN]u[12]x:}2{r:}").expect("should parse");
+    assert_eq!(lower, upper);
+    assert_eq!(
+        lower.read1_desc()[1],
+        GeomPiece::Fixed(NucStr::Seq("ACGTN".to_string()), 0)
+    );
+    assert_eq!(format!("{}", lower), "1{b[16]f[ACGTN]u[12]x:}2{r:}");
+}
+
+/// The default formatter should match `Display`, and the optional
+/// layouts should be applied to every read block.
+#[test]
+fn test_geom_desc_formatter() {
+    let frag_desc =
+        FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}i1{r[8]}").expect("should parse");
+    assert_eq!(
+        GeomDescFormatter::new().format(&frag_desc),
+        format!("{}", frag_desc)
+    );
+    assert_eq!(
+        GeomDescFormatter::new()
+            .with_spaces(true)
+            .format(&frag_desc),
+        "1{b[16] u[12] x:} 2{r:} i1{r[8]}"
+    );
+    assert_eq!(
+        GeomDescFormatter::new()
+            .with_read_numbers(false)
+            .format(&frag_desc),
+        "{b[16]u[12]x:}{r:}i1{r[8]}"
+    );
+}
+
+/// Only geometries without ranged or unbounded pieces are fully fixed,
+/// and only they have exact read lengths.
+#[test]
+fn test_fully_fixed() {
+    let frag_desc =
+        FragmentGeomDesc::try_from("1{b[16]f[ACG]u[12]}2{r[90]}").expect("should parse");
+    assert!(frag_desc.is_fully_fixed());
+    assert_eq!(frag_desc.total_fixed_len(), Some((31, 90)));
+
+    for arg in ["1{b[16]u[12]x:}2{r:}", "1{b[16]u[12]}2{r[90-100]}"] {
+        let frag_desc = FragmentGeomDesc::try_from(arg).expect("should parse");
+        assert!(!frag_desc.is_fully_fixed());
+        assert_eq!(frag_desc.total_fixed_len(), None);
+    }
+}
+
+/// Well-known geometries should be recognized, and anything else
+/// (even a near miss) should not.
+#[test]
+fn test_known_chemistry() {
+    for (arg, chem) in [
+        ("1{b[16]u[10]}2{r:}", Some(KnownChemistry::ChromiumV2)),
+        ("1{b[16]u[12]}2{r:}", Some(KnownChemistry::ChromiumV3)),
+        ("1{b[16]u[12]x:}2{r:}", Some(KnownChemistry::ChromiumV3)),
+        ("1{b[12]u[8]}2{r:}", Some(KnownChemistry::DropSeq)),
+        ("1{b[16]u[12]}2{r[91]}", None),
+        ("1{b[16]u[12]}", None),
+        ("1{b[16]u[12]}2{r:}i1{r[8]}", None),
+    ] {
+        let frag_desc = FragmentGeomDesc::try_from(arg).expect("should parse");
+        assert_eq!(frag_desc.known_chemistry(), chem);
+    }
+    assert_eq!(format!("{}", KnownChemistry::ChromiumV3), "10xv3");
+}
+
+/// Resolving unbounded pieces against known read lengths should yield a
+/// fully fixed geometry, and should fail when the lengths don't fit.
+#[test]
+fn test_resolve_unbounded() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    let resolved = frag_desc
+        .resolve_unbounded(Some(150), Some(91))
+        .expect("should resolve");
+    assert_eq!(format!("{}", resolved), "1{b[16]u[12]x[122]}2{r[91]}");
+    assert!(resolved.is_fully_fixed());
+
+    let resolved = frag_desc
+        .resolve_unbounded(None, Some(91))
+        .expect("should resolve");
+    assert_eq!(format!("{}", resolved), "1{b[16]u[12]x:}2{r[91]}");
+
+    // the barcode and UMI already consume all 28 bases
+    assert!(frag_desc.resolve_unbounded(Some(28), None).is_err());
+    // there is nothing left to resolve
+    assert!(resolved.resolve_unbounded(None, Some(91)).is_err());
+}
+
+/// Open-ended ranges should parse, round trip, and carry their minimum
+/// length into the length bounds.
+#[test]
+fn test_open_ended_range() {
+    let arg = "1{b[8-]f[ACGT]u[12]x:}2{r[50-]}";
+    let frag_desc = FragmentGeomDesc::try_from(arg).expect("should parse");
+    assert_eq!(
+        frag_desc.read1_desc()[0],
+        GeomPiece::Barcode(GeomLen::LenRangeFrom(8))
+    );
+    assert_eq!(arg, format!("{}", frag_desc));
+    assert!(frag_desc.is_complex_geometry());
+
+    let (r1, r2) = frag_desc.total_len_range();
+    assert_eq!(r1, GeomLenBounds { min: 24, max: None });
+    assert_eq!(r2, GeomLenBounds { min: 50, max: None });
+
+    let resolved = frag_desc
+        .resolve_unbounded(None, Some(91))
+        .expect("should resolve");
+    assert_eq!(
+        resolved.read2_desc(),
+        &[GeomPiece::ReadSeq(GeomLen::FixedLen(91), None)]
+    );
+    assert!(frag_desc.resolve_unbounded(None, Some(40)).is_err());
+
+    assert!(matches!(
+        FragmentGeomDesc::try_from("1{b[0-]f[ACGT]u[12]}2{r:}"),
+        Err(FragmentGeomError::ZeroLength { .. })
+    ));
+}
+
+/// Geometries should be obtainable from owned strings and via `str::parse`.
+#[test]
+fn test_from_str_and_string() {
+    let arg = "1{b[16]u[12]x:}2{r:}";
+    let expected = FragmentGeomDesc::try_from(arg).expect("should parse");
+
+    let parsed: FragmentGeomDesc = arg.parse().expect("should parse");
+    assert_eq!(parsed, expected);
+    let owned = FragmentGeomDesc::try_from(arg.to_string()).expect("should parse");
+    assert_eq!(owned, expected);
+
+    assert!("1{b[16]v[3]}2{r:}".parse::<FragmentGeomDesc>().is_err());
+}
+
+/// Looking up the piece covering an offset should follow the layout of the
+/// read, with unbounded pieces covering the rest of the read.
+#[test]
+fn test_piece_at() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r[90]}").expect("should parse");
+    assert_eq!(
+        frag_desc.piece_at(1, 0),
+        Some(&GeomPiece::Barcode(GeomLen::FixedLen(16)))
+    );
+    assert_eq!(
+        frag_desc.piece_at(1, 17),
+        Some(&GeomPiece::Umi(GeomLen::FixedLen(12)))
+    );
+    assert_eq!(
+        frag_desc.piece_at(1, 1000),
+        Some(&GeomPiece::Discard(GeomLen::Unbounded))
+    );
+    assert_eq!(
+        frag_desc.piece_at(2, 89),
+        Some(&GeomPiece::ReadSeq(GeomLen::FixedLen(90), None))
+    );
+    assert_eq!(frag_desc.piece_at(2, 90), None);
+    assert_eq!(frag_desc.piece_at(3, 0), None);
+    assert_eq!(frag_desc.piece_at(0, 0), None);
+}
+
+/// A ranged piece should be placed using its minimum length when looking up the
+/// piece covering an offset.
+#[test]
+fn test_piece_at_ranged() {
+    let frag_desc =
+        FragmentGeomDesc::try_from("1{b[9-10]f[ACGT]u[12]x:}2{r:}").expect("should parse");
+    assert_eq!(
+        frag_desc.piece_at(1, 8),
+        Some(&GeomPiece::Barcode(GeomLen::LenRange(9, 10)))
+    );
+    assert!(matches!(
+        frag_desc.piece_at(1, 9),
+        Some(GeomPiece::Fixed(..))
+    ));
+    assert_eq!(
+        frag_desc.piece_at(1, 13),
+        Some(&GeomPiece::Umi(GeomLen::FixedLen(12)))
+    );
+    assert_eq!(
+        frag_desc.piece_at(1, 25),
+        Some(&GeomPiece::Discard(GeomLen::Unbounded))
+    );
+}
+
+/// The Cell Ranger custom chemistry for 10x v3 should locate the barcode and
+/// UMI on R1 and the RNA on R2, and anchored layouts should be rejected.
+#[test]
+fn test_cellranger_desc() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    let cr_desc =
+        CellRangerGeomDesc::from_geom_pieces(frag_desc.read1_desc(), frag_desc.read2_desc())
+            .expect("should convert");
+    assert_eq!(
+        cr_desc.to_json(),
+        r#"{"barcode":[{"read_type":"R1","offset":0,"length":16}],"umi":[{"read_type":"R1","offset":16,"length":12}],"rna":{"read_type":"R2","offset":0,"length":null}}"#
+    );
+
+    for arg in ["1{b[16]f[ACG]u[12]x:}2{r:}", "1{b[8]x[4]b[8]u[12]x:}2{r:}"] {
+        let frag_desc = FragmentGeomDesc::try_from(arg).expect("should parse");
+        assert!(CellRangerGeomDesc::from_geom_pieces(
+            frag_desc.read1_desc(),
+            frag_desc.read2_desc()
+        )
+        .is_err());
+    }
+}
+
+/// The length helpers should report the bounds of each kind of `GeomLen`.
+#[test]
+fn test_geom_len_helpers() {
+    for (gl, min, max, variable) in [
+        (GeomLen::FixedLen(16), 16, Some(16), false),
+        (GeomLen::LenRange(9, 10), 9, Some(10), true),
+        (GeomLen::LenRangeFrom(8), 8, None, true),
+        (GeomLen::Unbounded, 0, None, true),
+    ] {
+        assert_eq!(gl.min_len(), min);
+        assert_eq!(gl.max_len(), max);
+        assert_eq!(gl.is_variable(), variable);
+    }
+}
+
+/// Read labels should be parsed, round trip, and leave label-free
+/// geometries unchanged.
+#[test]
+fn test_read_labels() {
+    let arg = r#"1{b[16]u[12]x:}:"cell barcode read"2{r:}"#;
+    let mut frag_desc = FragmentGeomDesc::try_from(arg).expect("should parse");
+    assert_eq!(frag_desc.read_label(1), Some("cell barcode read"));
+    assert_eq!(frag_desc.read_label(2), None);
+    assert_eq!(arg, format!("{}", frag_desc));
+
+    let unlabeled = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    assert!(unlabeled.read_labels.is_empty());
+    assert_ne!(frag_desc, unlabeled);
+    frag_desc.set_read_label(1, None);
+    assert_eq!(frag_desc, unlabeled);
+
+    frag_desc.set_read_label(2, Some("cDNA".to_string()));
+    assert_eq!(format!("{}", frag_desc), r#"1{b[16]u[12]x:}2{r:}:"cDNA""#);
+}
+
+/// Diffing two geometries should report the first differing piece, or
+/// `None` when they match.
+#[test]
+fn test_geom_diff() {
+    let v3 = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    assert_eq!(v3.diff(&v3), None);
+
+    let other = FragmentGeomDesc::try_from("1{b[14]u[12]x:}2{r:}").expect("should parse");
+    assert_eq!(
+        other.diff(&v3),
+        Some(GeomDiff {
+            read: 1,
+            index: 0,
+            left: Some(GeomPiece::Barcode(GeomLen::FixedLen(14))),
+            right: Some(GeomPiece::Barcode(GeomLen::FixedLen(16))),
+        })
+    );
+
+    let short = FragmentGeomDesc::try_from("1{b[16]u[12]}2{r:}").expect("should parse");
+    assert_eq!(
+        short.diff(&v3),
+        Some(GeomDiff {
+            read: 1,
+            index: 2,
+            left: None,
+            right: Some(GeomPiece::Discard(GeomLen::Unbounded)),
+        })
+    );
+
+    let single_end = FragmentGeomDesc::try_from("1{b[16]u[12]x:}").expect("should parse");
+    assert_eq!(
+        v3.diff(&single_end),
+        Some(GeomDiff {
+            read: 2,
+            index: 0,
+            left: Some(GeomPiece::ReadSeq(GeomLen::Unbounded, None)),
+            right: None,
+        })
+    );
+}
+
+/// With the `serde` feature enabled, a geometry can be parsed directly to
+/// JSON, with errors reported as plain strings.
+#[cfg(feature = "serde")]
+#[test]
+fn test_parse_to_json() {
+    let json = seq_geom_parser::parse_to_json("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    let de_desc: FragmentGeomDesc = serde_json::from_str(&json).expect("should deserialize");
+    assert_eq!(
+        de_desc,
+        FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse")
+    );
+
+    let err = seq_geom_parser::parse_to_json("1{b[16]v[3]}2{r:}").unwrap_err();
+    assert!(err.starts_with("could not parse geometry description"));
+}
+
+/// Adjacent variable-length pieces should be rejected as ambiguous, while
+/// an anchor between them keeps the layout valid.
+#[test]
+fn test_ambiguous_layout() {
+    assert_eq!(
+        FragmentGeomDesc::new(
+            vec![
+                GeomPiece::Barcode(GeomLen::LenRange(8, 10)),
+                GeomPiece::Umi(GeomLen::LenRange(8, 10)),
+            ],
+            vec![GeomPiece::ReadSeq(GeomLen::Unbounded, None)],
+        ),
+        Err(FragmentGeomError::AmbiguousLayout {
+            read: 1,
+            first: 0,
+            second: 1,
+        })
+    );
+    assert!(FragmentGeomDesc::try_from("1{b[9-10]f[ACCGT]u[12]}2{r:}").is_ok());
+    assert!(FragmentGeomDesc::try_from("1{b[9-10]f[ACCGT]u[12]b[10-12]}2{r:}").is_ok());
+}
+
+/// Pieces should be appendable to either read, and the read 1 of one
+/// geometry should be concatenable onto another.
+#[test]
+fn test_extend_and_concat() {
+    let mut frag_desc = FragmentGeomDesc::try_from("1{b[16]}").expect("should parse");
+    frag_desc.extend_read1([
+        GeomPiece::Umi(GeomLen::FixedLen(12)),
+        GeomPiece::Discard(GeomLen::Unbounded),
+    ]);
+    frag_desc.extend_read2([GeomPiece::ReadSeq(GeomLen::Unbounded, None)]);
+    assert_eq!(format!("{}", frag_desc), "1{b[16]u[12]x:}2{r:}");
+    assert!(frag_desc.validate().is_ok());
+
+    let bc = FragmentGeomDesc::try_from("1{b[16]}2{r:}").expect("should parse");
+    let umi = FragmentGeomDesc::try_from("1{u[12]x:}").expect("should parse");
+    assert_eq!(format!("{}", bc.concat_read1(&umi)), "1{b[16]u[12]x:}2{r:}");
+}
+
+/// A leading name should be captured and round trip, while unnamed
+/// descriptions parse as before.
+#[test]
+fn test_frag_name() {
+    let arg = "10xv3:1{b[16]u[12]x:}2{r:}";
+    let frag_desc = FragmentGeomDesc::try_from(arg).expect("should parse");
+    assert_eq!(frag_desc.name.as_deref(), Some("10xv3"));
+    assert_eq!(arg, format!("{}", frag_desc));
+
+    let unnamed = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    assert_eq!(unnamed.name, None);
+    assert_eq!(frag_desc.diff(&unnamed), None);
+}
+
+/// Each read should be renderable on its own, without its number or braces.
+#[test]
+fn test_read_string() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    assert_eq!(frag_desc.read_string(1).as_deref(), Some("b[16]u[12]x:"));
+    assert_eq!(frag_desc.read_string(2).as_deref(), Some("r:"));
+    assert_eq!(frag_desc.read_string(3), None);
+    assert_eq!(frag_desc.read_string(0), None);
+}
+
+/// The barcode key length should sum fixed-length barcodes across reads,
+/// and be undefined for variable-length (or absent) barcodes.
+#[test]
+fn test_barcode_key_len() {
+    for (arg, key_len) in [
+        ("1{b[16]u[12]x:}2{r:}", Some(16)),
+        ("1{b[8]f[ACGT]b[8]u[12]}2{r:}", Some(16)),
+        ("1{r:}2{u[10]x[30]b[8]x[30]b[8]x[30]b[8]}", Some(24)),
+        ("1{b[9-10]f[ACCGT]u[12]b[10]}2{r:}", None),
+        ("1{u[12]x:}2{r:}", None),
+        ("1{b[4000000000]}2{b[4000000000]r:}", None),
+    ] {
+        let frag_desc = FragmentGeomDesc::try_from(arg).expect("should parse");
+        assert_eq!(frag_desc.barcode_key_len(), key_len, "{}", arg);
+    }
+}
+
+/// Unbalanced braces and brackets should be reported as such, naming the
+/// offending read block.
+#[test]
+fn test_unbalanced_braces() {
+    for (arg, read) in [
+        ("1{b[16]u[12]", 1),
+        ("1{b[16]u[12]x:}}2{r:}", 1),
+        ("1{b[16]u[12]x:}2{r[91}", 2),
+        ("1{b[16]u12]x:}2{r:}", 1),
+        ("1{b[16]u[12]x:2{r:}", 1),
+    ] {
+        assert_eq!(
+            FragmentGeomDesc::try_from(arg),
+            Err(FragmentGeomError::UnbalancedBraces { read }),
+            "{}",
+            arg
+        );
+    }
+    // braces within a quoted label don't count
+    assert!(FragmentGeomDesc::try_from(r#"1{b[16]u[12]x:}:"{cb"2{r:}"#).is_ok());
+}
+
+/// Every piece should report its length, with fixed sequences reporting
+/// the length of the sequence.
+#[test]
+fn test_geom_piece_len() {
+    assert_eq!(
+        GeomPiece::Umi(GeomLen::LenRange(8, 10)).geom_len(),
+        GeomLen::LenRange(8, 10)
+    );
+    assert_eq!(
+        GeomPiece::Tag("sb".to_string(), GeomLen::Unbounded).geom_len(),
+        GeomLen::Unbounded
+    );
+    assert_eq!(
+        GeomPiece::Fixed(NucStr::Seq("ACCGT".to_string()), 0).geom_len(),
+        GeomLen::FixedLen(5)
+    );
+}
+
+/// The UMI-tools pattern for 10x v3 should be 16 cell barcode bases
+/// followed by 12 UMI bases, and anchored layouts should be rejected.
+#[test]
+fn test_umi_tools_desc() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    let umi_tools_desc =
+        UmiToolsGeomDesc::from_geom_pieces(frag_desc.read1_desc(), frag_desc.read2_desc())
+            .expect("should convert");
+    assert_eq!(umi_tools_desc.bc_pattern, "CCCCCCCCCCCCCCCCNNNNNNNNNNNN");
+    assert_eq!(umi_tools_desc.bc_pattern2, None);
+
+    let mut cmd = std::process::Command::new("umi_tools");
+    umi_tools_desc.append(&mut cmd);
+    assert_eq!(
+        cmd.get_args().collect::<Vec<_>>(),
+        ["--bc-pattern=CCCCCCCCCCCCCCCCNNNNNNNNNNNN"]
+    );
+
+    for arg in [
+        "1{b[16]f[ACG]u[12]x:}2{r:}",
+        "1{b[9-10]f[ACG]u[12]}2{r:}",
+        "1{b[16]u[12]r[50]}2{r:}",
+    ] {
+        let frag_desc = FragmentGeomDesc::try_from(arg).expect("should parse");
+        assert!(
+            UmiToolsGeomDesc::from_geom_pieces(frag_desc.read1_desc(), frag_desc.read2_desc())
+                .is_err()
+        );
+    }
+}
+
+/// The nominal length should use range midpoints, and the chosen default
+/// for unbounded pieces.
+#[test]
+fn test_nominal_len() {
+    let frag_desc =
+        FragmentGeomDesc::try_from("1{b[9-10]f[ACCGT]u[12]b[10]x:}2{r:}").expect("should parse");
+    assert_eq!(frag_desc.nominal_len(), (36, 0));
+    assert_eq!(frag_desc.nominal_len_with(100), (136, 100));
+
+    // huge ranges and unbounded defaults saturate rather than overflowing.
+    let frag_desc = FragmentGeomDesc::try_from("1{b[3000000000-3000000002]f[ACGT]u[12]}2{r:}")
+        .expect("should parse");
+    assert_eq!(frag_desc.nominal_len(), (3000000017, 0));
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    assert_eq!(frag_desc.nominal_len_with(u32::MAX), (u32::MAX, u32::MAX));
+}
+
+/// A trailing newline, whitespace, or a single `;` should be ignored.
+#[test]
+fn test_trailing_terminator() {
+    let expected = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    for arg in [
+        "1{b[16]u[12]x:}2{r:}\n",
+        "1{b[16]u[12]x:}2{r:}\r\n",
+        "1{b[16]u[12]x:}2{r:};",
+        "1{b[16]u[12]x:}2{r:} ;\n",
+    ] {
+        assert_eq!(
+            FragmentGeomDesc::try_from(arg).as_ref(),
+            Ok(&expected),
+            "{:?}",
+            arg
+        );
+    }
+    assert!(FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:};;").is_err());
+    assert!(FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}; x").is_err());
+}
+
+/// Only a read holding a single biological sequence piece is pure.
+#[test]
+fn test_read_is_pure_readseq() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    assert!(!frag_desc.read_is_pure_readseq(1));
+    assert!(frag_desc.read_is_pure_readseq(2));
+    assert!(!frag_desc.read_is_pure_readseq(3));
+
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]}2{r[90-100]}").expect("should parse");
+    assert!(frag_desc.read_is_pure_readseq(2));
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]}2{x[4]r:}").expect("should parse");
+    assert!(!frag_desc.read_is_pure_readseq(2));
+}
+
+/// A visitor that sums the minimum length of each type of piece.
+#[derive(Default)]
+struct LenByType {
+    barcode: u32,
+    umi: u32,
+    fixed: u32,
+    other: u32,
+}
+
+impl GeomVisitor for LenByType {
+    fn visit_barcode(&mut self, _read: u8, len: &GeomLen) {
+        self.barcode += len.min_len();
+    }
+    fn visit_umi(&mut self, _read: u8, len: &GeomLen) {
+        self.umi += len.min_len();
+    }
+    fn visit_fixed(&mut self, _read: u8, seq: &NucStr, _max_mismatch: u32) {
+        let NucStr::Seq(s) = seq;
+        self.fixed += s.len() as u32;
+    }
+    fn visit_discard(&mut self, _read: u8, len: &GeomLen) {
+        self.other += len.min_len();
+    }
+    fn visit_readseq(&mut self, _read: u8, len: &GeomLen) {
+        self.other += len.min_len();
+    }
+}
+
+/// Visiting a geometry should dispatch every piece to the matching method.
+#[test]
+fn test_geom_visitor() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[9-10]f[ACCGT]u[12]b[10]x[4]}2{r[90]}")
+        .expect("should parse");
+    let mut v = LenByType::default();
+    frag_desc.accept(&mut v);
+    assert_eq!((v.barcode, v.umi, v.fixed, v.other), (19, 12, 5, 94));
+}
+
+/// Geometries that differ only in how discards are split up, or in how a fixed
+/// length is written, should be semantically (but not structurally) equal.
+#[test]
+fn test_semantically_eq() {
+    let a = FragmentGeomDesc::try_from("1{b[16]u[12]x[4]x[4]}2{r:}").expect("should parse");
+    let b = FragmentGeomDesc::try_from("1{b[16]u[12]x[8]}2{r:}").expect("should parse");
+    assert_ne!(a, b);
+    assert!(a.semantically_eq(&b));
+
+    let c = FragmentGeomDesc::new(
+        vec![
+            GeomPiece::Barcode(GeomLen::LenRange(16, 16)),
+            GeomPiece::Umi(GeomLen::FixedLen(12)),
+            GeomPiece::Discard(GeomLen::FixedLen(8)),
+        ],
+        vec![GeomPiece::ReadSeq(GeomLen::Unbounded, None)],
+    )
+    .expect("should build");
+    assert!(c.semantically_eq(&b));
+
+    let d = FragmentGeomDesc::try_from("1{b[16]u[12]x[9]}2{r:}").expect("should parse");
+    assert!(!d.semantically_eq(&b));
+}
+
+/// A fixed sequence may carry a mismatch tolerance, which defaults to 0 and
+/// round-trips through `Display`.
+#[test]
+fn test_fixed_seq_max_mismatch() {
+    let frag_desc =
+        FragmentGeomDesc::try_from("1{b[16]f[ACCGT]~1u[12]f[TTT]}2{r:}").expect("should parse");
+    assert_eq!(
+        frag_desc.read1_desc()[1],
+        GeomPiece::Fixed(NucStr::Seq("ACCGT".to_string()), 1)
+    );
+    assert_eq!(
+        frag_desc.read1_desc()[3],
+        GeomPiece::Fixed(NucStr::Seq("TTT".to_string()), 0)
+    );
+    assert_eq!(frag_desc.to_string(), "1{b[16]f[ACCGT]~1u[12]f[TTT]}2{r:}");
+    assert_eq!(
+        frag_desc.to_piscem_string().expect("should convert"),
+        "1{b[16]f[ACCGT]u[12]f[TTT]}2{r:}"
+    );
+    assert!(FragmentGeomDesc::try_from("1{b[16]f[ACCGT]~}2{r:}").is_err());
+
+    // a tolerance as large as the anchor itself would match anything
+    assert!(FragmentGeomDesc::try_from("1{b[16]f[ACGT]~3u[12]}2{r:}").is_ok());
+    for (arg, segment, max_mismatch) in [
+        ("1{b[16]f[ACGT]~10u[12]}2{r:}", "f[ACGT]~10", 10),
+        ("1{b[16]f[ACGT]~4u[12]}2{r:}", "f[ACGT]~4", 4),
+    ] {
+        assert_eq!(
+            FragmentGeomDesc::try_from(arg),
+            Err(FragmentGeomError::TooManyMismatches {
+                segment: segment.to_string(),
+                max_mismatch,
+                len: 4
+            })
+        );
+    }
+}
+
+/// The UMI and barcode lengths should only be reported when there is exactly one
+/// such segment.
+#[test]
+fn test_umi_and_barcode_len() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[10-12]}2{r:}").expect("should parse");
+    assert_eq!(frag_desc.umi_len(), Some(GeomLen::LenRange(10, 12)));
+    assert_eq!(frag_desc.barcode_len(), Some(GeomLen::FixedLen(16)));
+
+    let frag_desc =
+        FragmentGeomDesc::try_from("1{b[8]f[ACGT]b[8]u[6]}2{u[6]r:}").expect("should parse");
+    assert_eq!(frag_desc.umi_len(), None);
+    assert_eq!(frag_desc.barcode_len(), None);
+
+    let frag_desc = FragmentGeomDesc::try_from("1{r:}2{r:}").expect("should parse");
+    assert_eq!(frag_desc.umi_len(), None);
+    assert_eq!(frag_desc.barcode_len(), None);
+}
+
+/// Parsing many geometries should skip blank and comment lines, and report the
+/// line number of each result.
+#[test]
+fn test_parse_many() {
+    let input = "# chromium v3\n1{b[16]u[12]x:}2{r:}\n\n  # dropseq\n1{b[12]u[8]x:}2{r:}\n1{b[16]u[12]}2{q:}\n";
+    let parsed = parse_many(input);
+    assert_eq!(
+        parsed.iter().map(|(l, _)| *l).collect::<Vec<usize>>(),
+        vec![2, 5, 6]
+    );
+    assert_eq!(
+        parsed[0].1.as_ref().expect("should parse").to_string(),
+        "1{b[16]u[12]x:}2{r:}"
+    );
+    assert_eq!(
+        parsed[1].1.as_ref().expect("should parse").to_string(),
+        "1{b[12]u[8]x:}2{r:}"
+    );
+    assert!(parsed[2].1.is_err());
+}
+
+/// Changing the length of a piece should preserve its type, and leave fixed
+/// sequence anchors unchanged.
+#[test]
+fn test_geom_piece_with_len() {
+    assert_eq!(
+        GeomPiece::ReadSeq(GeomLen::Unbounded, None).with_len(GeomLen::FixedLen(90)),
+        GeomPiece::ReadSeq(GeomLen::FixedLen(90), None)
+    );
+    assert_eq!(
+        GeomPiece::Tag("bc".to_string(), GeomLen::FixedLen(8)).with_len(GeomLen::LenRange(8, 10)),
+        GeomPiece::Tag("bc".to_string(), GeomLen::LenRange(8, 10))
+    );
+    let anchor = GeomPiece::Fixed(NucStr::Seq("ACGT".to_string()), 1);
+    assert_eq!(anchor.with_len(GeomLen::FixedLen(10)), anchor);
+}
+
+/// Each piece should report its type, regardless of its length.
+#[test]
+fn test_geom_piece_kind() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[9-10]f[ACCGT]u[12]s[8]t<feat>[4]x:}2{r:}")
+        .expect("should parse");
+    let kinds = frag_desc
+        .iter_pieces()
+        .map(|(_, gp)| gp.kind())
+        .collect::<Vec<SegmentKind>>();
+    assert_eq!(
+        kinds,
+        vec![
+            SegmentKind::Barcode,
+            SegmentKind::Fixed,
+            SegmentKind::Umi,
+            SegmentKind::SampleBarcode,
+            SegmentKind::Tag,
+            SegmentKind::Discard,
+            SegmentKind::ReadSeq,
+        ]
+    );
+}
+
+/// A geometry should only be satisfiable by reads long enough to hold each of
+/// their pieces.
+#[test]
+fn test_is_satisfiable() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    assert!(frag_desc.is_satisfiable(28, 91).is_err());
+    assert!(frag_desc.is_satisfiable(29, 91).is_ok());
+    assert_eq!(
+        frag_desc.is_satisfiable(26, 91),
+        Err(FragmentGeomError::ReadTooShort {
+            read: 1,
+            needed: 29,
+            got: 26
+        })
+    );
+
+    let frag_desc =
+        FragmentGeomDesc::try_from("1{b[9-10]f[ACCGT]u[12]}2{r[90]}").expect("should parse");
+    assert!(frag_desc.is_satisfiable(26, 90).is_ok());
+    assert_eq!(
+        frag_desc.is_satisfiable(26, 89),
+        Err(FragmentGeomError::ReadTooShort {
+            read: 2,
+            needed: 90,
+            got: 89
+        })
+    );
+}
+
+/// Homopolymer segments should parse, round-trip through `Display`, complement
+/// their base, and be treated as discarded sequence by the exporters.
+#[test]
+fn test_homopolymer_segment() {
+    let frag_desc =
+        FragmentGeomDesc::try_from("1{b[16]u[12]p<t>[30]x:}2{r:}").expect("should parse");
+    assert_eq!(
+        frag_desc.read1_desc()[2],
+        GeomPiece::Homopolymer(b'T', GeomLen::FixedLen(30))
+    );
+    assert_eq!(frag_desc.to_string(), "1{b[16]u[12]p<T>[30]x:}2{r:}");
+    assert_eq!(frag_desc.counts().homopolymer, 1);
+    assert_eq!(
+        GeomPiece::Homopolymer(b'T', GeomLen::Unbounded).reverse_complement(),
+        GeomPiece::Homopolymer(b'A', GeomLen::Unbounded)
+    );
+    assert_eq!(
+        frag_desc.to_piscem_string().expect("should convert"),
+        "1{b[16]u[12]x[30]x:}2{r:}"
+    );
+    let starsolo_desc =
+        StarsoloGeomDesc::from_geom_pieces(frag_desc.read1_desc(), frag_desc.read2_desc())
+            .expect("should convert");
+    assert_eq!((starsolo_desc.umi_start, starsolo_desc.umi_len), (17, 12));
+
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]p<A>:}2{r:}").expect("should parse");
+    assert_eq!(frag_desc.to_string(), "1{b[16]u[12]p<A>:}2{r:}");
+    assert!(FragmentGeomDesc::try_from("1{b[16]u[12]p<N>[10]}2{r:}").is_err());
+    assert_eq!(
+        FragmentGeomDesc::new(
+            vec![GeomPiece::Homopolymer(b'N', GeomLen::FixedLen(10))],
+            vec![GeomPiece::ReadSeq(GeomLen::Unbounded, None)]
+        ),
+        Err(FragmentGeomError::InvalidHomopolymerBase('N'))
+    );
+}
+
+/// A cloned geometry should be equal to, but independent of, the original.
+#[test]
+fn test_clone_frag_desc() {
+    let frag_desc =
+        FragmentGeomDesc::try_from("v3:1{b[16]u[12]x:}:\"R1\"2{r:}").expect("should parse");
+    let mut copy = frag_desc.clone();
+    assert_eq!(copy, frag_desc);
+    copy.extend_read2([GeomPiece::Discard(GeomLen::FixedLen(4))]);
+    assert_ne!(copy, frag_desc);
+    assert_eq!(frag_desc.to_string(), "v3:1{b[16]u[12]x:}:\"R1\"2{r:}");
+}
+
+/// The summary of a geometry should collect its counts, fixed lengths, and
+/// anchors, and render them as a report.
+#[test]
+fn test_geom_summary() {
+    let frag_desc =
+        FragmentGeomDesc::try_from("1{b[9-10]f[ACCGT]u[12]b[10]}2{r:}").expect("should parse");
+    let summary = frag_desc.summary();
+    assert_eq!(summary.counts, frag_desc.counts());
+    assert_eq!(summary.fixed_len, vec![27, 0]);
+    assert_eq!(summary.unbounded, vec![false, true]);
+    assert_eq!(summary.anchors, vec![(1, "ACCGT".to_string())]);
+    assert_eq!(
+        summary.to_string(),
+        "segments: 2 barcode, 0 sample barcode, 1 UMI, 1 read, 0 discard, 1 fixed, 0 tag, 0 homopolymer, 0 spacer\n\
+         read 1: 27 fixed-length bases, bounded\n\
+         read 2: 0 fixed-length bases, unbounded\n\
+         anchors: ACCGT (read 1)"
+    );
+}
+
+/// A maximum fixed sequence length should reject longer anchors, and impose no
+/// limit by default.
+#[test]
+fn test_parse_opts_max_fixed_seq_len() {
+    let desc = "1{b[16]f[ACCGTACCGT]u[12]}2{r:}";
+    let opts = ParseOpts::new().with_max_fixed_seq_len(Some(8));
+    assert_eq!(
+        FragmentGeomDesc::try_from_with_opts(desc, opts),
+        Err(FragmentGeomError::FixedSeqTooLong { len: 10, max: 8 })
+    );
+    let opts = ParseOpts::new().with_max_fixed_seq_len(Some(10));
+    assert!(FragmentGeomDesc::try_from_with_opts(desc, opts).is_ok());
+    assert_eq!(
+        FragmentGeomDesc::try_from_with_opts(desc, ParseOpts::default()),
+        FragmentGeomDesc::try_from(desc)
+    );
+}
+
+#[cfg(feature = "proptest")]
+proptest::proptest! {
+    /// Every generated geometry should be valid, and should parse back from its
+    /// `Display` unchanged.
+    #[test]
+    fn prop_display_round_trip(frag_desc in seq_geom_parser::arb_frag_geom_desc()) {
+        proptest::prop_assert!(frag_desc.validate().is_ok());
+        let rendered = frag_desc.to_string();
+        proptest::prop_assert_eq!(FragmentGeomDesc::try_from(rendered.as_str()), Ok(frag_desc));
+    }
+}
+
+/// Anchor sequences should be listed in order of appearance, optionally without
+/// repeats.
+#[test]
+fn test_anchor_sequences() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[8]f[ACGT]b[8]f[TTGA]u[12]}2{x[4-6]f[ACGT]r:}")
+        .expect("should parse");
+    assert_eq!(frag_desc.anchor_sequences(), vec!["ACGT", "TTGA", "ACGT"]);
+    assert_eq!(frag_desc.distinct_anchor_sequences(), vec!["ACGT", "TTGA"]);
+
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    assert!(frag_desc.anchor_sequences().is_empty());
+}
+
+/// A single-end fragment whose read 1 holds the barcode, UMI, and biological
+/// sequence (as in some long-read protocols) should be exported with the read
+/// interval on read 1, and without a read 2.
+#[test]
+fn test_single_read_fragment_export() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]r:}").expect("should parse");
+    assert_eq!(frag_desc.num_reads(), 1);
+    assert!(frag_desc.read2_desc().is_empty());
+    assert_eq!(frag_desc.to_string(), "1{b[16]u[12]r:}");
+
+    let (read1_desc, read2_desc) = frag_desc.read_pair().expect("should have at most 2 reads");
+    let salmon_desc =
+        SalmonSeparateGeomDesc::from_geom_pieces(read1_desc, read2_desc).expect("should convert");
+    assert_eq!(
+        salmon_desc,
+        SalmonSeparateGeomDesc {
+            barcode_desc: "1[1-16]".to_string(),
+            umi_desc: "1[17-28]".to_string(),
+            read_desc: "1[29-end]".to_string(),
+        }
+    );
+    let piscem_desc = PiscemGeomDesc::from_geom_pieces(read1_desc, read2_desc);
+    assert_eq!(piscem_desc.read2_desc, "");
+    assert_eq!(piscem_desc.to_string(), "1{b[16]u[12]r:}");
+    assert_eq!(
+        frag_desc.to_piscem_string().expect("should convert"),
+        "1{b[16]u[12]r:}"
+    );
+}
+
+/// A geometry should be a prefix of another when each of its reads is a prefix of
+/// the corresponding read.
+#[test]
+fn test_is_prefix_of() {
+    let v3 = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    let bc_only = FragmentGeomDesc::try_from("1{b[16]}2{r:}").expect("should parse");
+    let single_end = FragmentGeomDesc::try_from("1{b[16]u[12]}").expect("should parse");
+    let v2 = FragmentGeomDesc::try_from("1{b[16]u[10]x:}2{r:}").expect("should parse");
+    assert!(bc_only.is_prefix_of(&v3));
+    assert!(single_end.is_prefix_of(&v3));
+    assert!(v3.is_prefix_of(&v3));
+    assert!(!v3.is_prefix_of(&bc_only));
+    assert!(!v3.is_prefix_of(&single_end));
+    assert!(!v2.is_prefix_of(&v3));
+}
+
+/// Parsing with spans should report the byte range of each piece in the input.
+#[test]
+fn test_try_from_with_spans() {
+    let arg = "1{b[16]u[12]x:}2{r:}i1{x[8]}";
+    let (frag_desc, spans) = FragmentGeomDesc::try_from_with_spans(arg).expect("should parse");
+    assert_eq!(
+        frag_desc,
+        FragmentGeomDesc::try_from(arg).expect("should parse")
+    );
+    assert_eq!(
+        spans,
+        vec![
+            (1, 0, 2..7),
+            (1, 1, 7..12),
+            (1, 2, 12..14),
+            (2, 0, 17..19),
+            (3, 0, 23..27),
+        ]
+    );
+    assert_eq!(&arg[spans[1].2.clone()], "u[12]");
+    assert!(FragmentGeomDesc::try_from_with_spans("1{b[16]u[12]x:}2{q:}").is_err());
+}
+
+/// A single-point range is parsed as a ranged segment, and should then be
+/// normalized to the fixed length it describes, so that the geometry is
+/// classified as simple and round-trips in fixed form.
+#[test]
+fn test_single_point_range_is_fixed() {
+    let frag_desc = FragmentGeomDesc::try_from("1{u[12]b[16-16]}2{r:}").expect("should parse");
+    assert_eq!(
+        frag_desc.read1_desc()[1],
+        GeomPiece::Barcode(GeomLen::FixedLen(16))
+    );
+    assert!(!frag_desc.read1_desc()[1].is_complex());
+    assert!(frag_desc.is_simple_geometry());
+    assert!(!frag_desc.is_complex_geometry());
+    assert_eq!(frag_desc.to_string(), "1{u[12]b[16]}2{r:}");
+
+    let frag_desc = FragmentGeomDesc::try_from("1{b[016-16]}2{r:}").expect("should parse");
+    assert_eq!(
+        frag_desc.read1_desc()[0],
+        GeomPiece::Barcode(GeomLen::FixedLen(16))
+    );
+    assert_eq!(
+        "u[12-12]".parse::<GeomPiece>(),
+        Ok(GeomPiece::Umi(GeomLen::FixedLen(12)))
+    );
+}
+
+/// Individual pieces should parse on their own, and invalid pieces should be
+/// rejected.
+#[test]
+fn test_parse_geom_piece() {
+    assert_eq!(
+        "b[16]".parse::<GeomPiece>(),
+        Ok(GeomPiece::Barcode(GeomLen::FixedLen(16)))
+    );
+    assert_eq!(
+        "f[acgt]~1".parse::<GeomPiece>(),
+        Ok(GeomPiece::Fixed(NucStr::Seq("ACGT".to_string()), 1))
+    );
+    assert_eq!(
+        "u[10-12]".parse::<GeomPiece>(),
+        Ok(GeomPiece::Umi(GeomLen::LenRange(10, 12)))
+    );
+    assert_eq!(
+        "r:".parse::<GeomPiece>(),
+        Ok(GeomPiece::ReadSeq(GeomLen::Unbounded, None))
+    );
+    assert!(matches!(
+        "b[16]u[12]".parse::<GeomPiece>(),
+        Err(FragmentGeomError::ParseFailure { pos: 5, .. })
+    ));
+    assert!(matches!(
+        "b[12-10]".parse::<GeomPiece>(),
+        Err(FragmentGeomError::InvalidRange { .. })
+    ));
+    assert!(matches!(
+        "x[0]".parse::<GeomPiece>(),
+        Err(FragmentGeomError::ZeroLength { .. })
+    ));
+}
+
+/// A guide sequence between known flanks, and the 10x long-read layout, should
+/// produce the corresponding flexiplex search patterns.
+#[test]
+fn test_flexiplex_desc() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]}2{x:r[20]f[GTTTAAGAGCTAAGCTGGAA]x:}")
+        .expect("should parse");
+    let flexiplex_desc =
+        FlexiplexGeomDesc::from_geom_pieces(frag_desc.read2_desc()).expect("should convert");
+    assert_eq!(
+        flexiplex_desc,
+        FlexiplexGeomDesc {
+            left_flank: None,
+            barcode_len: 20,
+            umi_len: None,
+            right_flank: Some("GTTTAAGAGCTAAGCTGGAA".to_string()),
+        }
+    );
+    let mut cmd = std::process::Command::new("flexiplex");
+    flexiplex_desc.append(&mut cmd);
+    assert_eq!(
+        cmd.get_args().collect::<Vec<_>>(),
+        vec!["-b", "????????????????????", "-x", "GTTTAAGAGCTAAGCTGGAA"]
+    );
+
+    let frag_desc = FragmentGeomDesc::try_from("1{x:f[CTACACGACGCTCTTCCGATCT]b[16]u[12]p<T>[9]x:}")
+        .expect("should parse");
+    let flexiplex_desc =
+        FlexiplexGeomDesc::from_geom_pieces(frag_desc.read1_desc()).expect("should convert");
+    let mut cmd = std::process::Command::new("flexiplex");
+    flexiplex_desc.append(&mut cmd);
+    assert_eq!(
+        cmd.get_args().collect::<Vec<_>>(),
+        vec![
+            "-x",
+            "CTACACGACGCTCTTCCGATCT",
+            "-b",
+            "????????????????",
+            "-u",
+            "????????????",
+            "-x",
+            "TTTTTTTTT"
+        ]
+    );
+
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]b[8]}2{r:}").expect("should parse");
+    assert!(FlexiplexGeomDesc::from_geom_pieces(frag_desc.read1_desc()).is_err());
+    assert!(FlexiplexGeomDesc::from_geom_pieces(frag_desc.read2_desc()).is_err());
+}
+
+/// Removing the discards should keep every other piece in order.
+#[test]
+fn test_without_discards() {
+    let frag_desc =
+        FragmentGeomDesc::try_from("1{b[16]x[4]u[12]x:}2{x[3]f[ACGT]r:}").expect("should parse");
+    let stripped = frag_desc.without_discards();
+    assert_eq!(stripped.to_string(), "1{b[16]u[12]}2{f[ACGT]r:}");
+    assert_eq!(stripped.counts().discard, 0);
+    assert_eq!(frag_desc.counts().discard, 3);
+}
+
+/// Lengths summing past `u32::MAX` (or written with more digits than a `u32`
+/// holds) should be rejected cleanly rather than wrapping around or panicking,
+/// and a maximum segment length should be enforced.
+#[test]
+fn test_offset_overflow() {
+    assert_eq!(
+        FragmentGeomDesc::try_from("1{b[4294967295]u[12]}2{r:}"),
+        Err(FragmentGeomError::OffsetOverflow {
+            segment: "u[12]".to_string()
+        })
+    );
+    assert!(FragmentGeomDesc::try_from("1{b[4294967295]}2{r:}").is_ok());
+
+    let pieces = vec![
+        GeomPiece::Barcode(GeomLen::FixedLen(u32::MAX - 4)),
+        GeomPiece::Umi(GeomLen::FixedLen(12)),
+    ];
+    let err = geom_piece_intervals(&pieces).expect_err("should overflow");
+    assert_eq!(
+        err.downcast_ref::<FragmentGeomError>(),
+        Some(&FragmentGeomError::OffsetOverflow {
+            segment: "u[12]".to_string()
+        })
+    );
+    let pieces = vec![
+        GeomPiece::Barcode(GeomLen::FixedLen(u32::MAX)),
+        GeomPiece::Umi(GeomLen::FixedLen(12)),
+    ];
+    for err in [
+        StarsoloGeomDesc::from_geom_pieces(&pieces, &[]).expect_err("should overflow"),
+        BustoolsGeomDesc::from_geom_pieces(&pieces, &[]).expect_err("should overflow"),
+    ] {
+        assert_eq!(
+            err.downcast_ref::<FragmentGeomError>(),
+            Some(&FragmentGeomError::OffsetOverflow {
+                segment: "u[12]".to_string()
+            })
+        );
+    }
+
+    // numbers that don't fit in a u32 are parse failures, not panics.
+    for (arg, at) in [
+        ("1{b[99999999999]}2{r:}", 4),
+        ("1{b[16-99999999999]f[A]u[12]}2{r:}", 7),
+        ("1{b[99999999999-]}2{r:}", 4),
+        ("1{b[16]f[ACGT]~99999999999u[12]}2{r:}", 15),
+        ("1{b[16]u[12]}2{r:}i1{x[99999999999]}", 23),
+    ] {
+        match FragmentGeomDesc::try_from(arg) {
+            Err(FragmentGeomError::ParseFailure { pos, .. }) => assert_eq!(pos, at, "{}", arg),
+            r => panic!("expected a parse failure for {}, but got {:?}", arg, r),
+        }
+    }
+    assert!(matches!(
+        FragmentGeomDesc::from_fields(None, "b[99999999999]", "r:"),
+        Err(FragmentGeomError::ParseFailure { pos: 2, .. })
+    ));
+    assert!(matches!(
+        "b[99999999999]".parse::<GeomPiece>(),
+        Err(FragmentGeomError::ParseFailure { pos: 2, .. })
+    ));
+
+    // lengths of unvalidated geometries (e.g. from `map_pieces`) saturate rather than
+    // overflow, while validating them still reports the overflow.
+    let huge = GeomPiece::Discard(GeomLen::FixedLen(u32::MAX - 1));
+    let mut frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]b[4]}2{r:}")
+        .expect("should parse")
+        .map_pieces(|_, gp| match gp {
+            GeomPiece::Barcode(GeomLen::FixedLen(16)) | GeomPiece::Umi(_) => huge.clone(),
+            gp => gp.clone(),
+        });
+    assert_eq!(
+        frag_desc.validate(),
+        Err(FragmentGeomError::OffsetOverflow {
+            segment: huge.to_string()
+        })
+    );
+    let bounds = frag_desc.total_len_range().0;
+    assert_eq!((bounds.min, bounds.max), (u32::MAX, Some(u32::MAX)));
+    assert_eq!(frag_desc.fixed_prefix_len(), (u32::MAX, 0));
+    assert_eq!(frag_desc.min_required_len(1), u32::MAX);
+    assert!(frag_desc.barcode_segments().is_empty());
+    frag_desc.canonicalize();
+    assert_eq!(
+        frag_desc.read1_desc()[0],
+        GeomPiece::Discard(GeomLen::FixedLen(u32::MAX))
+    );
+
+    let opts = ParseOpts::new().with_max_segment_len(Some(1000));
+    assert_eq!(
+        FragmentGeomDesc::try_from_with_opts("1{b[16]u[12]x[50-2000]f[ACGT]}2{r:}", opts),
+        Err(FragmentGeomError::SegmentTooLong {
+            segment: "x[50-2000]".to_string(),
+            max: 1000
+        })
+    );
+    assert!(FragmentGeomDesc::try_from_with_opts("1{b[16]u[12]x:}2{r[1000]}", opts).is_ok());
+}
+
+/// `as_args` should yield the same arguments that `append` adds to a command.
+#[test]
+fn test_as_args() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    let starsolo_desc =
+        StarsoloGeomDesc::from_geom_pieces(frag_desc.read1_desc(), frag_desc.read2_desc())
+            .expect("should convert");
+    assert_eq!(
+        starsolo_desc.as_args(),
+        [
+            "--soloType",
+            "CB_UMI_Simple",
+            "--soloCBstart",
+            "1",
+            "--soloCBlen",
+            "16",
+            "--soloUMIstart",
+            "17",
+            "--soloUMIlen",
+            "12"
+        ]
+    );
+
+    let piscem_desc =
+        PiscemGeomDesc::from_geom_pieces(frag_desc.read1_desc(), frag_desc.read2_desc());
+    assert_eq!(
+        piscem_desc.as_args(),
+        ["--geometry", "1{b[16]u[12]x:}2{r:}"]
+    );
+
+    let mut cmd = std::process::Command::new("STAR");
+    starsolo_desc.append(&mut cmd);
+    assert_eq!(
+        cmd.get_args()
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect::<Vec<_>>(),
+        starsolo_desc.as_args()
+    );
+
+    // an implementor need only provide `as_args` to get `append`
+    struct Threads(usize);
+    impl AppendToCmdArgs for Threads {
+        fn as_args(&self) -> Vec<String> {
+            vec!["-p".to_string(), self.0.to_string()]
+        }
+    }
+    let mut cmd = std::process::Command::new("salmon");
+    Threads(8).append(&mut cmd);
+    assert_eq!(cmd.get_args().collect::<Vec<_>>(), ["-p", "8"]);
+}
+
+/// Building a geometry from separate name and read fields should match parsing
+/// the equivalent description string, and malformed fields should be rejected.
+#[test]
+fn test_from_fields() {
+    let frag_desc =
+        FragmentGeomDesc::from_fields(Some("10xv3"), "b[16]u[12]x:", "r:").expect("should parse");
+    assert_eq!(
+        frag_desc,
+        FragmentGeomDesc::try_from("10xv3:1{b[16]u[12]x:}2{r:}").expect("should parse")
+    );
+    assert_eq!(frag_desc.read_string(1).as_deref(), Some("b[16]u[12]x:"));
+
+    let single_end = FragmentGeomDesc::from_fields(None, "b[16]u[12]r:", "").expect("should parse");
+    assert!(single_end.read2_desc().is_empty());
+    assert_eq!(single_end.name, None);
+
+    assert!(matches!(
+        FragmentGeomDesc::from_fields(None, "b[16]v[3]", "r:"),
+        Err(FragmentGeomError::ParseFailure { pos: 5, .. })
+    ));
+    assert!(FragmentGeomDesc::from_fields(None, "{b[16]}", "r:").is_err());
+    assert!(FragmentGeomDesc::from_fields(Some("bad name"), "b[16]", "r:").is_err());
+    assert!(matches!(
+        FragmentGeomDesc::from_fields(None, "b[0]", "r:"),
+        Err(FragmentGeomError::ZeroLength { .. })
+    ));
+}
+
+/// Barcode segments split across both reads should be ordered read 1 first, then
+/// left-to-right within each read.
+#[test]
+fn test_barcode_concatenation_order() {
+    let frag_desc =
+        FragmentGeomDesc::try_from("1{b[8]x[4]u[12]b[6]}2{x[2]b[10]r:}").expect("should parse");
+    assert_eq!(
+        frag_desc.barcode_concatenation_order(),
+        [(1, 0), (1, 3), (2, 1)]
+    );
+
+    let frag_desc = FragmentGeomDesc::try_from("1{u[12]}2{b[16]r:}").expect("should parse");
+    assert_eq!(frag_desc.barcode_concatenation_order(), [(2, 0)]);
+
+    let frag_desc = FragmentGeomDesc::try_from("1{r:}2{r:}").expect("should parse");
+    assert!(frag_desc.barcode_concatenation_order().is_empty());
+}
+
+/// Inserting and removing pieces should produce a new, validated geometry and
+/// leave the original untouched.
+#[test]
+fn test_insert_remove_piece() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+
+    let inserted = frag_desc
+        .insert_piece(1, 1, GeomPiece::Discard(GeomLen::FixedLen(4)))
+        .expect("should insert");
+    assert_eq!(inserted.to_string(), "1{b[16]x[4]u[12]x:}2{r:}");
+    assert_eq!(frag_desc.to_string(), "1{b[16]u[12]x:}2{r:}");
+
+    let appended = frag_desc
+        .insert_piece(2, 0, GeomPiece::Umi(GeomLen::FixedLen(8)))
+        .expect("should insert");
+    assert_eq!(appended.to_string(), "1{b[16]u[12]x:}2{u[8]r:}");
+
+    let removed = inserted.remove_piece(1, 1).expect("should remove");
+    assert_eq!(removed, frag_desc);
+
+    assert_eq!(
+        frag_desc.insert_piece(1, 3, GeomPiece::ReadSeq(GeomLen::Unbounded, None)),
+        Err(FragmentGeomError::MultipleUnbounded { read: 1 })
+    );
+    assert_eq!(
+        frag_desc.insert_piece(1, 4, GeomPiece::Umi(GeomLen::FixedLen(8))),
+        Err(FragmentGeomError::PieceIndexOutOfRange {
+            read: 1,
+            index: 4,
+            len: 3
+        })
+    );
+    assert_eq!(
+        frag_desc.insert_piece(3, 0, GeomPiece::Umi(GeomLen::FixedLen(8))),
+        Err(FragmentGeomError::MissingRead(3))
+    );
+    assert_eq!(
+        frag_desc.remove_piece(2, 0),
+        Err(FragmentGeomError::MissingRead(2))
+    );
+    assert!(frag_desc.remove_piece(1, 3).is_err());
+}
+
+/// A repetition count applies to the single base preceding it, and is expanded
+/// at parse time, so the geometry round-trips to the expanded sequence.
+#[test]
+fn test_fixed_seq_repetition_count() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]f[N{5}]u[12]}2{r:}").expect("should parse");
+    assert_eq!(
+        frag_desc.read1_desc()[1],
+        GeomPiece::Fixed(NucStr::Seq("NNNNN".to_string()), 0)
+    );
+    assert_eq!(frag_desc.to_string(), "1{b[16]f[NNNNN]u[12]}2{r:}");
+
+    let frag_desc =
+        FragmentGeomDesc::try_from("1{b[16]f[ACG{2}T]~1u[12]}2{r:}").expect("should parse");
+    assert_eq!(
+        frag_desc.read1_desc()[1],
+        GeomPiece::Fixed(NucStr::Seq("ACGGT".to_string()), 1)
+    );
+    assert_eq!(
+        "f[t{3}a]".parse::<GeomPiece>().expect("should parse"),
+        GeomPiece::Fixed(NucStr::Seq("TTTA".to_string()), 0)
+    );
+
+    for arg in [
+        "1{b[16]f[N{0}]u[12]}2{r:}",
+        "1{b[16]f[{3}]u[12]}2{r:}",
+        "1{b[16]f[AC{}]u[12]}2{r:}",
+        "1{b[16]f[N{10000}]u[12]}2{r:}",
+        "1{b[16]f[N{5]u[12]}2{r:}",
+    ] {
+        assert!(FragmentGeomDesc::try_from(arg).is_err(), "{}", arg);
+    }
+}
+
+/// A read is only used if it holds something other than discarded sequence.
+#[test]
+fn test_uses_read() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    assert!(frag_desc.uses_read(1));
+    assert!(frag_desc.uses_read(2));
+    assert!(!frag_desc.uses_read(0));
+    assert!(!frag_desc.uses_read(3));
+
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]r:}2{x:}").expect("should parse");
+    assert!(frag_desc.uses_read(1));
+    assert!(!frag_desc.uses_read(2));
+
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]r:}2{x[10]x:}").expect("should parse");
+    assert!(!frag_desc.uses_read(2));
+
+    let single_end = FragmentGeomDesc::try_from("1{b[16]u[12]r:}").expect("should parse");
+    assert!(!single_end.uses_read(2));
+}
+
+/// Two single-read geometries should combine into the equivalent paired-end one,
+/// and inputs that already have a read 2 should be rejected.
+#[test]
+fn test_from_reads() {
+    let r1 = FragmentGeomDesc::try_from("10xv3:1{b[16]u[12]x:}:\"R1\"").expect("should parse");
+    let r2 = FragmentGeomDesc::try_from("1{r:}:\"R2\"").expect("should parse");
+    let paired = FragmentGeomDesc::from_reads(r1.clone(), r2.clone()).expect("should combine");
+    assert_eq!(
+        paired,
+        FragmentGeomDesc::try_from("10xv3:1{b[16]u[12]x:}:\"R1\"2{r:}:\"R2\"")
+            .expect("should parse")
+    );
+
+    let two_reads = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    assert_eq!(
+        FragmentGeomDesc::from_reads(two_reads.clone(), r2.clone()),
+        Err(FragmentGeomError::TooManyReads { found: 2, max: 1 })
+    );
+    assert_eq!(
+        FragmentGeomDesc::from_reads(r1, two_reads),
+        Err(FragmentGeomError::TooManyReads { found: 2, max: 1 })
+    );
+
+    // a read 1 emptied by `without_discards` still counts as a read
+    let emptied = FragmentGeomDesc::try_from("1{x:}2{r:}")
+        .expect("should parse")
+        .without_discards();
+    assert_eq!(emptied.num_reads(), 1);
+    assert_eq!(
+        FragmentGeomDesc::from_reads(emptied, r2),
+        Err(FragmentGeomError::TooManyReads { found: 2, max: 1 })
+    );
+}
+
+/// Pieces should sort by kind and then by length, and geometries with the same
+/// pieces in a different layout should compare equal as multisets.
+#[test]
+fn test_piece_ordering() {
+    let mut pieces = vec![
+        GeomPiece::ReadSeq(GeomLen::Unbounded, None),
+        GeomPiece::Umi(GeomLen::FixedLen(12)),
+        GeomPiece::Barcode(GeomLen::LenRange(9, 10)),
+        GeomPiece::Barcode(GeomLen::FixedLen(16)),
+        GeomPiece::Barcode(GeomLen::FixedLen(8)),
+    ];
+    pieces.sort();
+    assert_eq!(
+        pieces,
+        [
+            GeomPiece::Barcode(GeomLen::FixedLen(8)),
+            GeomPiece::Barcode(GeomLen::FixedLen(16)),
+            GeomPiece::Barcode(GeomLen::LenRange(9, 10)),
+            GeomPiece::Umi(GeomLen::FixedLen(12)),
+            GeomPiece::ReadSeq(GeomLen::Unbounded, None),
+        ]
+    );
+
+    let a = FragmentGeomDesc::try_from("1{b[16]u[12]}2{r:}").expect("should parse");
+    let b = FragmentGeomDesc::try_from("1{u[12]b[16]r:}").expect("should parse");
+    let c = FragmentGeomDesc::try_from("1{b[16]u[10]}2{r:}").expect("should parse");
+    let d = FragmentGeomDesc::try_from("1{b[16]u[12]u[12]}2{r:}").expect("should parse");
+    assert!(a.piece_multiset_eq(&b));
+    assert!(!a.piece_multiset_eq(&c));
+    assert!(!a.piece_multiset_eq(&d));
+}
+
+/// Intervals should be reported in the requested coordinate system, with the
+/// default matching the 1-based inclusive intervals that salmon expects.
+#[test]
+fn test_interval_coordinates() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    let half_open = Coordinates {
+        base: CoordBase::Zero,
+        end: EndKind::Exclusive,
+    };
+    let intervals = geom_piece_intervals_with_coords(frag_desc.read1_desc(), half_open)
+        .expect("should compute intervals");
+    assert_eq!(intervals.barcode, [(0, Some(16))]);
+    assert_eq!(intervals.umi, [(16, Some(28))]);
+
+    let zero_inclusive = Coordinates {
+        base: CoordBase::Zero,
+        end: EndKind::Inclusive,
+    };
+    let intervals = geom_piece_intervals_with_coords(frag_desc.read1_desc(), zero_inclusive)
+        .expect("should compute intervals");
+    assert_eq!(intervals.barcode, [(0, Some(15))]);
+    assert_eq!(intervals.umi, [(16, Some(27))]);
+
+    assert_eq!(
+        SalmonSeparateGeomDesc::from_geom_pieces_with_coords(
+            frag_desc.read1_desc(),
+            frag_desc.read2_desc(),
+            Coordinates::default()
+        )
+        .expect("should convert"),
+        SalmonSeparateGeomDesc::from_geom_pieces(frag_desc.read1_desc(), frag_desc.read2_desc())
+            .expect("should convert")
+    );
+    let salmon_desc = SalmonSeparateGeomDesc::from_geom_pieces_with_coords(
+        frag_desc.read1_desc(),
+        frag_desc.read2_desc(),
+        half_open,
+    )
+    .expect("should convert");
+    assert_eq!(salmon_desc.barcode_desc, "1[0-16]");
+    assert_eq!(salmon_desc.umi_desc, "1[16-28]");
+    assert_eq!(salmon_desc.read_desc, "2[0-end]");
+}
+
+/// Geometries that salmon can't express, such as those with fixed sequence
+/// anchors, should produce an error rather than a panic.
+#[test]
+fn test_salmon_unsupported() {
+    for arg in [
+        "1{b[9-10]f[ACCGT]u[12]}2{r:}",
+        "1{b[16]f[ACCGT]u[12]}2{r:}",
+        "1{s[8]b[16]u[12]x:}2{r:}",
+        "1{b[16]u[10-12]}2{r:}",
+    ] {
+        let frag_desc = FragmentGeomDesc::try_from(arg).expect("should parse");
+        assert!(
+            SalmonSeparateGeomDesc::from_geom_pieces(
+                frag_desc.read1_desc(),
+                frag_desc.read2_desc()
+            )
+            .is_err(),
+            "{}",
+            arg
+        );
+    }
+}
+
+/// Geometries should be classified by whether they contain anchors, ranges, or
+/// unbounded pieces, consistently with `is_complex_geometry`.
+#[test]
+fn test_complexity() {
+    for (arg, complexity) in [
+        ("1{b[16]u[12]}2{r[91]}", GeomComplexity::FullyFixed),
+        ("1{b[16]u[12]x:}2{r:}", GeomComplexity::FixedWithUnbounded),
+        ("1{b[16]u[10-12]}2{r:}", GeomComplexity::HasRanges),
+        ("1{b[16]f[ACGT]u[12]}2{r:}", GeomComplexity::HasAnchors),
+        (
+            "1{b[9-10]f[ACCGT]u[12]}2{r:}",
+            GeomComplexity::HasAnchorsAndRanges,
+        ),
+        ("1{b[16]u[12]}2{r:}i1{x[2-4]}", GeomComplexity::HasRanges),
+    ] {
+        let frag_desc = FragmentGeomDesc::try_from(arg).expect("should parse");
+        assert_eq!(frag_desc.complexity(), complexity, "{}", arg);
+        assert_eq!(
+            frag_desc.is_complex_geometry(),
+            !matches!(
+                complexity,
+                GeomComplexity::FullyFixed | GeomComplexity::FixedWithUnbounded
+            )
+        );
+    }
+}
+
+/// Declared read lengths should round-trip, be checked against the pieces of their
+/// read, and serve as the default lengths when resolving unbounded pieces.
+#[test]
+fn test_read_len_hint() {
+    let frag_desc = FragmentGeomDesc::try_from("1@28{b[16]u[12]}2@150{r:}").expect("should parse");
+    assert_eq!(frag_desc.read_len_hint(1), Some(28));
+    assert_eq!(frag_desc.read_len_hint(2), Some(150));
+    assert_eq!(frag_desc.to_string(), "1@28{b[16]u[12]}2@150{r:}");
+
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2@91{r:}").expect("should parse");
+    assert_eq!(frag_desc.read_len_hint(1), None);
+    let resolved = frag_desc
+        .resolve_unbounded(None, None)
+        .expect("should resolve");
+    assert_eq!(resolved.to_string(), "1{b[16]u[12]x:}2@91{r[91]}");
+    let resolved = frag_desc
+        .resolve_unbounded(Some(150), Some(100))
+        .expect("should resolve");
+    assert_eq!(resolved.to_string(), "1{b[16]u[12]x[122]}2@91{r[100]}");
+
+    let plain = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    assert!(plain.read_lens.is_empty());
+    assert!(frag_desc.semantically_eq(&plain));
+    assert_ne!(frag_desc, plain);
+
+    assert_eq!(
+        FragmentGeomDesc::try_from("1@20{b[16]u[12]}2{r:}"),
+        Err(FragmentGeomError::ReadTooShort {
+            read: 1,
+            needed: 28,
+            got: 20
+        })
+    );
+    // the declared length is kept when the read numbers are omitted
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2@150{r:}").expect("should parse");
+    assert_eq!(
+        GeomDescFormatter::new()
+            .with_read_numbers(false)
+            .format(&frag_desc),
+        "{b[16]u[12]x:}@150{r:}"
+    );
+
+    assert!(FragmentGeomDesc::try_from("1@99999999999{b[16]u[12]}2{r:}").is_err());
+    assert!(FragmentGeomDesc::try_from("1@{b[16]u[12]}2{r:}").is_err());
+}
+
+/// Every kind of piece should be masked as a discard of the same length.
+#[test]
+fn test_as_discard() {
+    for (desc, discard) in [
+        ("b[16]", "x[16]"),
+        ("s[8]", "x[8]"),
+        ("u[10-12]", "x[10-12]"),
+        ("x:", "x:"),
+        ("r[50-]", "x[50-]"),
+        ("f[ACGT]~1", "x[4]"),
+        ("t<feat>[6]", "x[6]"),
+        ("p<T>:", "x:"),
+    ] {
+        let gp = desc.parse::<GeomPiece>().expect("should parse");
+        assert_eq!(gp.as_discard().to_string(), discard);
+    }
+}
+
+/// Mapping the pieces of a geometry should rewrite each piece in place, passing
+/// along the number of the read containing it.
+#[test]
+fn test_map_pieces() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]x:}:\"R1\"2{u[8]r:}i1{b[8]}")
+        .expect("should parse");
+    let masked = frag_desc.map_pieces(|read, gp| match gp {
+        GeomPiece::Umi(_) if read == 1 => gp.as_discard(),
+        gp => gp.clone(),
+    });
+    assert_eq!(
+        masked.to_string(),
+        "1{b[16]x[12]x:}:\"R1\"2{u[8]r:}i1{b[8]}"
+    );
+
+    let index_masked = frag_desc.map_pieces(|read, gp| {
+        if read == 3 {
+            gp.as_discard()
+        } else {
+            gp.clone()
+        }
+    });
+    assert_eq!(
+        index_masked.index1_desc,
+        [GeomPiece::Discard(GeomLen::FixedLen(8))]
+    );
+
+    assert_eq!(frag_desc.map_pieces(|_, gp| gp.clone()), frag_desc);
+}
+
+/// A UMI may be split into adjacent parts, but not spread across separate regions.
+#[test]
+fn test_validate_umi() {
+    for arg in [
+        "1{b[16]u[12]x:}2{r:}",
+        "1{b[16]u[6]u[6]x:}2{r:}",
+        "1{b[16]x:}2{r:}",
+    ] {
+        let frag_desc = FragmentGeomDesc::try_from(arg).expect("should parse");
+        assert_eq!(frag_desc.validate_umi(), Ok(()), "{}", arg);
+    }
+
+    let frag_desc = FragmentGeomDesc::try_from("1{u[12]}2{u[8]r:}").expect("should parse");
+    assert_eq!(
+        frag_desc.validate_umi(),
+        Err(FragmentGeomError::MultipleUmiRegions { reads: vec![1, 2] })
+    );
+    let frag_desc = FragmentGeomDesc::try_from("1{u[6]b[16]u[6]}2{r:}").expect("should parse");
+    assert_eq!(
+        frag_desc.validate_umi(),
+        Err(FragmentGeomError::MultipleUmiRegions { reads: vec![1, 1] })
+    );
+}
+
+/// A `piscem` geometry string should parse back into the geometry it was
+/// exported from.
+#[test]
+fn test_from_piscem_str() {
+    for arg in [
+        "1{b[16]u[12]x:}2{r:}",
+        "1{b[9-10]f[ACCGT]u[12]b[10]}2{r:}",
+        "1{b[16]u[12]r:}",
+    ] {
+        let frag_desc = FragmentGeomDesc::try_from(arg).expect("should parse");
+        let (read1_desc, read2_desc) = frag_desc.read_pair().expect("should have at most 2 reads");
+        let piscem_desc = PiscemGeomDesc::from_geom_pieces(read1_desc, read2_desc);
+        assert_eq!(
+            FragmentGeomDesc::from_piscem_str(&piscem_desc.to_string()).expect("should parse"),
+            frag_desc
+        );
+    }
+    assert_eq!(
+        FragmentGeomDesc::from_piscem_str("1{b[16]}2{u[12]}3{r:}"),
+        Err(FragmentGeomError::TooManyReads { found: 3, max: 2 })
+    );
+}
+
+/// Geometries that differ only outside of their barcode and UMI segments should
+/// share a tag layout.
+#[test]
+fn test_same_tag_layout() {
+    let v3 = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    for (arg, same) in [
+        ("1{b[16]u[12]}2{r[91]}", true),
+        ("1{b[16]f[ACGT]u[12]x:}2{x[10]r:}", true),
+        ("1{u[12]b[16]}2{r:}", false),
+        ("1{b[16]u[10]x:}2{r:}", false),
+        ("1{b[16]x:}2{u[12]r:}", false),
+    ] {
+        let frag_desc = FragmentGeomDesc::try_from(arg).expect("should parse");
+        assert_eq!(v3.same_tag_layout(&frag_desc), same, "{}", arg);
+    }
+}
+
+/// The DOT rendering of 10x v3 should have one row of colored boxes per read.
+#[test]
+fn test_to_dot() {
+    let frag_desc = FragmentGeomDesc::try_from("10xv3:1{b[16]u[12]x:}2{r:}").expect("should parse");
+    assert_eq!(
+        frag_desc.to_dot(),
+        r#"digraph fragment {
+    label="10xv3";
+    node [shape=plaintext];
+    R1 [label=<<table border="0" cellborder="1" cellspacing="0"><tr><td>R1</td><td bgcolor="lightblue">barcode<br/>16</td><td bgcolor="palegreen">UMI<br/>12</td><td bgcolor="lightgrey">discard<br/>*</td></tr></table>>];
+    R2 [label=<<table border="0" cellborder="1" cellspacing="0"><tr><td>R2</td><td bgcolor="wheat">read<br/>*</td></tr></table>>];
+    R1 -> R2 [style=invis];
+}
+"#
+    );
+
+    let frag_desc = FragmentGeomDesc::try_from("1{b[9-10]f[ACCGT]u[12]b[10]}2{r:}i1{x[8]}")
+        .expect("should parse");
+    let dot = frag_desc.to_dot();
+    assert!(dot.contains("<td bgcolor=\"lightblue\">barcode<br/>9-10</td>"));
+    assert!(dot.contains("<td bgcolor=\"gold\">ACCGT<br/>5</td>"));
+    assert!(dot.contains("R2 -> I1 [style=invis];"));
+    assert!(!dot.contains("label=\""));
+}
+
+/// Reverse-oriented reads should be marked after the read number, round-trip
+/// through `Display`, and default to `Forward` when unmarked.
+#[test]
+fn test_read_orientation() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2-{r:}").expect("should parse");
+    assert_eq!(frag_desc.read_orientation(1), Orientation::Forward);
+    assert_eq!(frag_desc.read_orientation(2), Orientation::Reverse);
+    assert_eq!(frag_desc.read_orientation(3), Orientation::Forward);
+    assert_eq!(frag_desc.to_string(), "1{b[16]u[12]x:}2-{r:}");
+    assert_eq!(
+        GeomDescFormatter::new()
+            .with_read_numbers(false)
+            .format(&frag_desc),
+        "{b[16]u[12]x:}-{r:}"
+    );
+
+    let frag_desc = FragmentGeomDesc::try_from("1-@28{b[16]u[12]}2{r:}").expect("should parse");
+    assert_eq!(frag_desc.read_orientation(1), Orientation::Reverse);
+    assert_eq!(frag_desc.read_len_hint(1), Some(28));
+    assert_eq!(frag_desc.to_string(), "1-@28{b[16]u[12]}2{r:}");
+    assert_eq!(
+        GeomDescFormatter::new()
+            .with_read_numbers(false)
+            .with_spaces(true)
+            .format(&frag_desc),
+        "-@28{b[16] u[12]} {r:}"
+    );
+
+    let mut plain = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    assert!(plain.read_orientations.is_empty());
+    plain.set_read_orientation(2, Orientation::Reverse);
+    assert_eq!(plain.to_string(), "1{b[16]u[12]x:}2-{r:}");
+    plain.set_read_orientation(2, Orientation::Forward);
+    assert!(plain.read_orientations.is_empty());
+
+    assert!(FragmentGeomDesc::try_from("1{b[16]u[12]x:}2+{r:}").is_err());
+    assert!(FragmentGeomDesc::try_from("1{b[16]u[12]x:}2@91-{r:}").is_err());
+}
+
+/// The salmon intervals of a single read should carry its read number, and a
+/// read with an anchor (as in CRISPR feature barcoding layouts) should be rejected.
+#[test]
+fn test_salmon_intervals_for_read() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    assert_eq!(
+        SalmonSeparateGeomDesc::intervals_for_read(frag_desc.read1_desc(), 1)
+            .expect("should convert"),
+        ("1[1-16]".to_string(), "1[17-28]".to_string(), String::new())
+    );
+    assert_eq!(
+        SalmonSeparateGeomDesc::intervals_for_read(frag_desc.read2_desc(), 2)
+            .expect("should convert"),
+        (String::new(), String::new(), "2[1-end]".to_string())
+    );
+
+    let crispr = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{x[10]f[GTTTAAGAGCTAAGCTGGAA]x:}")
+        .expect("should parse");
+    assert!(SalmonSeparateGeomDesc::intervals_for_read(crispr.read2_desc(), 2).is_err());
+}
+
+/// A valid geometry followed by trailing text should be reported as such, rather
+/// than as a malformed geometry.
+#[test]
+fn test_trailing_input() {
+    assert_eq!(
+        FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}_flargbarg"),
+        Err(FragmentGeomError::TrailingInput {
+            at: 20,
+            text: "_flargbarg".to_string()
+        })
+    );
+    assert_eq!(
+        FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}; 1{b[16]}"),
+        Err(FragmentGeomError::TrailingInput {
+            at: 22,
+            text: "1{b[16]}".to_string()
+        })
+    );
+    // a malformed read block is still a parse failure
+    assert!(matches!(
+        FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}3{b[}"),
+        Err(FragmentGeomError::UnbalancedBraces { .. } | FragmentGeomError::ParseFailure { .. })
+    ));
+    assert!(matches!(
+        FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}3{v[3]}"),
+        Err(FragmentGeomError::ParseFailure { .. })
+    ));
+    // problems with the geometry itself take precedence
+    assert!(matches!(
+        FragmentGeomDesc::try_from("1{b[0]u[12]x:}2{r:}_flargbarg"),
+        Err(FragmentGeomError::ZeroLength { .. })
+    ));
+}
+
+/// Building exporter descriptions from a whole fragment should match building
+/// them from its read slices, and reject fragments with too many reads.
+#[test]
+fn test_exporters_from_fragment() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    assert_eq!(
+        PiscemGeomDesc::from_fragment(&frag_desc).expect("should convert"),
+        PiscemGeomDesc::from_geom_pieces(frag_desc.read1_desc(), frag_desc.read2_desc())
+    );
+    assert_eq!(
+        SalmonSeparateGeomDesc::from_fragment(&frag_desc).expect("should convert"),
+        SalmonSeparateGeomDesc::from_geom_pieces(frag_desc.read1_desc(), frag_desc.read2_desc())
+            .expect("should convert")
+    );
+
+    let three_reads = FragmentGeomDesc::try_from("1{b[16]}2{u[12]}3{r:}").expect("should parse");
+    assert_eq!(
+        PiscemGeomDesc::from_fragment(&three_reads),
+        Err(FragmentGeomError::TooManyReads { found: 3, max: 2 })
+    );
+    assert!(SalmonSeparateGeomDesc::from_fragment(&three_reads).is_err());
+}
+
+/// Read segments may carry a pairing id, which round-trips through `Display`
+/// and is ignored by the length predicates.
+#[test]
+fn test_read_pairing_id() {
+    let frag_desc =
+        FragmentGeomDesc::try_from("1{b[16]u[12]r#2:}2{r#1[50]x:}3{r#1:}").expect("should parse");
+    assert_eq!(
+        frag_desc.to_string(),
+        "1{b[16]u[12]r#2:}2{r#1[50]x:}3{r#1:}"
+    );
+    assert_eq!(
+        frag_desc.read2_desc(),
+        &[
+            GeomPiece::ReadSeq(GeomLen::FixedLen(50), Some(1)),
+            GeomPiece::Discard(GeomLen::Unbounded)
+        ]
+    );
+    assert_eq!(
+        frag_desc.paired_read_segments(),
+        vec![(2, 0), (3, 0), (1, 2)]
+    );
+    assert!(GeomPiece::ReadSeq(GeomLen::FixedLen(50), Some(1)).is_fixed_len());
+
+    let paired = FragmentGeomDesc::try_from("1{b[16]u[12]r#1:}2{r#1:}").expect("should parse");
+    assert_eq!(
+        paired.to_piscem_string().expect("should convert"),
+        "1{b[16]u[12]r:}2{r:}"
+    );
+
+    let plain = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    assert_eq!(
+        plain.read2_desc(),
+        &[GeomPiece::ReadSeq(GeomLen::Unbounded, None)]
+    );
+    assert!(plain.paired_read_segments().is_empty());
+}
+
+/// The minimum length of a read should cover its technical pieces, but not any
+/// read sequence after the last of them.
+#[test]
+fn test_min_required_len() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    assert_eq!(frag_desc.min_required_len(1), 28);
+    assert_eq!(frag_desc.min_required_len(2), 0);
+    assert_eq!(frag_desc.min_required_len(3), 0);
+
+    let frag_desc =
+        FragmentGeomDesc::try_from("1{b[9-10]f[GTACTCTGCGTTGATACCACTGCTT]b[10]u[12]r:}2{r:}")
+            .expect("should parse");
+    assert_eq!(frag_desc.min_required_len(1), 9 + 25 + 10 + 12);
+}
+
+/// Spacer segments should round-trip through `Display`, keep their length, and
+/// be rendered as discarded sequence by exporters with no notion of a spacer.
+#[test]
+fn test_spacer_segment() {
+    for s in [
+        "1{b[16]g[4]u[12]x:}2{r:}",
+        "1{b[16]g[3-5]f[ACGT]u[12]x:}2{r:}",
+        "1{b[16]u[12]g[2-]}2{r:}",
+        "1{b[16]u[12]g:}2{r:}",
+    ] {
+        let frag_desc = FragmentGeomDesc::try_from(s).expect("should parse");
+        assert_eq!(frag_desc.to_string(), s);
+    }
+
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]g[4]u[12]x:}2{r:}").expect("should parse");
+    assert_eq!(
+        frag_desc.read1_desc()[1],
+        GeomPiece::Spacer(GeomLen::FixedLen(4))
+    );
+    assert_eq!(frag_desc.read1_desc()[1].kind(), SegmentKind::Spacer);
+    assert!(frag_desc.read1_desc()[1].is_fixed_len());
+    assert_eq!(frag_desc.counts().spacer, 1);
+    assert_eq!(
+        geom_piece_intervals(frag_desc.read1_desc())
+            .expect("should compute intervals")
+            .spacer,
+        vec![(17, Some(20))]
+    );
+    assert_eq!(
+        frag_desc.to_piscem_string().expect("should convert"),
+        "1{b[16]x[4]u[12]x:}2{r:}"
+    );
+}
+
+/// The number of reads should count only the non-empty read blocks.
+#[test]
+fn test_num_reads() {
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]x:}2{r:}").expect("should parse");
+    assert_eq!(frag_desc.num_reads(), 2);
+    let frag_desc = FragmentGeomDesc::try_from("1{b[16]u[12]r:}").expect("should parse");
+    assert_eq!(frag_desc.num_reads(), 1);
+    let frag_desc =
+        FragmentGeomDesc::try_from("1{b[16]u[12]r:}2{x:}i1{b[8]}").expect("should parse");
+    assert_eq!(frag_desc.num_reads(), 2);
+    assert_eq!(frag_desc.without_discards().num_reads(), 1);
+}
+
+/// Semantically equal geometries should share a fingerprint, and geometries
+/// that differ should not.
+#[test]
+fn test_fingerprint() {
+    let split = FragmentGeomDesc::try_from("1{b[16]x[4]x[4]u[12]x:}2{r:}").expect("should parse");
+    let merged = FragmentGeomDesc::try_from("1{b[16]x[8]u[12]x:}2{r:}").expect("should parse");
+    let named =
+        FragmentGeomDesc::try_from("chromium_v3:1{b[16]x[8]u[12]x:}2{r:}").expect("should parse");
+    assert_ne!(split.to_string(), merged.to_string());
+    assert_eq!(split.fingerprint(), merged.fingerprint());
+    assert_eq!(merged.fingerprint(), named.fingerprint());
+    assert_eq!(merged.fingerprint().len(), 16);
+    assert!(merged.fingerprint().chars().all(|c| c.is_ascii_hexdigit()));
+
+    let other = FragmentGeomDesc::try_from("1{b[16]x[8]u[10]x:}2{r:}").expect("should parse");
+    assert_ne!(merged.fingerprint(), other.fingerprint());
+}
+
+/// The alphabet of fixed sequences should be configurable, with `U` optionally
+/// normalized to `T`, while the default accepts any IUPAC base.
+#[test]
+fn test_parse_opts_alphabet() {
+    let rna = "1{b[16]f[ACGU]u[12]x:}2{r:}";
+    let dna = "1{b[16]f[ACGT]u[12]x:}2{r:}";
+    let iupac = "1{b[16]f[ACGN]u[12]x:}2{r:}";
+    for desc in [rna, dna, iupac] {
+        assert!(FragmentGeomDesc::try_from(desc).is_ok());
+        assert!(FragmentGeomDesc::try_from_with_opts(desc, ParseOpts::default()).is_ok());
+    }
+
+    let opts = ParseOpts::new().with_alphabet(Alphabet::Dna);
+    assert!(FragmentGeomDesc::try_from_with_opts(dna, opts).is_ok());
+    assert_eq!(
+        FragmentGeomDesc::try_from_with_opts(rna, opts),
+        Err(FragmentGeomError::InvalidBase {
+            base: 'U',
+            alphabet: Alphabet::Dna
+        })
+    );
+
+    let opts = ParseOpts::new().with_alphabet(Alphabet::Rna);
+    let frag_desc = FragmentGeomDesc::try_from_with_opts(rna, opts).expect("should parse");
+    assert_eq!(frag_desc.to_string(), rna);
+    assert_eq!(
+        FragmentGeomDesc::try_from_with_opts(iupac, opts),
+        Err(FragmentGeomError::InvalidBase {
+            base: 'N',
+            alphabet: Alphabet::Rna
+        })
+    );
+
+    let opts = opts.with_u_to_t(true);
+    let frag_desc = FragmentGeomDesc::try_from_with_opts(rna, opts).expect("should parse");
+    assert_eq!(frag_desc.to_string(), dna);
+}